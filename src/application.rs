@@ -1,167 +1,447 @@
 use crate::{
+    config::{ApplicationListActions, ApplicationListConfig, ApplicationListOrder, SearchConfig},
     db::{Db, DbEntry},
+    exec::{Exec, ExecContext},
     icon::{APPLICATION_ICON_MAP, CATEGORY_ICON_MAP, Icon},
+    locale::Locale,
+    search,
+    xdg::DesktopFile,
 };
-use ini::Ini;
-use ratatui::{
-    style::{Color, Style},
-    text::Span,
+use ini::{Ini, ParseOption, Properties};
+use ratatui::{style::Style, text::Span};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fs,
+    num::NonZero,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
 };
-use std::{env::home_dir, ffi::CString, fs, path::Path};
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Clone, Debug)]
 pub struct Application {
+    pub id: String,
     pub name: String,
-    pub filename: CString,
-    pub args: Vec<CString>,
+    pub untranslated_name: String,
+    pub generic_name: Option<String>,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub exec: Exec,
     pub terminal: bool,
+    pub hidden: bool,
     pub comment: Option<String>,
     pub icon: Icon,
     pub db_entry: DbEntry,
+    pub actions: Vec<Action>,
+    /// desktop file ID of the application this entry is an action of
+    pub parent: Option<String>,
 }
 
-impl Application {
-    pub fn from_file(path: &str, db: &Db) -> Option<Self> {
+/// A `[Desktop Action <id>]` section of a desktop entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Action {
+    pub name: String,
+    pub untranslated_name: String,
+    pub exec: Exec,
+}
+
+impl Action {
+    fn from_section(
+        section: &Properties,
+        context: &ExecContext,
+        locales: &[String],
+    ) -> Option<Self> {
+        let untranslated_name = unescape(section.get("Name")?);
+        let name = get_localized(section, "Name", locales)?;
+        let desktop_icon = section.get("Icon").map(unescape);
+        let context = ExecContext {
+            icon: desktop_icon.as_deref().or(context.icon),
+            ..*context
+        };
+        let exec = Exec::parse(&unescape(section.get("Exec")?), &context)?;
+        Some(Self {
+            name,
+            untranslated_name,
+            exec,
+        })
+    }
+}
+
+/// The parsed `[Desktop Entry]` group of a desktop file, independent of the launch database.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DesktopEntry {
+    pub id: String,
+    name: String,
+    untranslated_name: String,
+    generic_name: Option<String>,
+    keywords: Vec<String>,
+    categories: Vec<String>,
+    exec: Exec,
+    terminal: bool,
+    comment: Option<String>,
+    actions: Vec<Action>,
+    /// `NoDisplay` or `Hidden`
+    no_display: bool,
+    only_show_in: Vec<String>,
+    not_show_in: Vec<String>,
+    try_exec: Option<String>,
+}
+
+impl DesktopEntry {
+    /// Upper bound of threads used by [`DesktopEntry::parse_all`].
+    const MAX_PARSE_THREADS: usize = 8;
+
+    /// Parses `desktop_files` across a bounded number of threads.
+    ///
+    /// The result has one item per desktop file, in the same order.
+    pub fn parse_all(desktop_files: &[DesktopFile], locales: &[String]) -> Vec<Option<Self>> {
+        let threads = thread::available_parallelism()
+            .map_or(1, NonZero::get)
+            .min(Self::MAX_PARSE_THREADS)
+            .min(desktop_files.len());
+        if threads <= 1 {
+            return desktop_files
+                .iter()
+                .map(|desktop_file| Self::parse(desktop_file, locales))
+                .collect();
+        }
+        let next = AtomicUsize::new(0);
+        let mut parsed = thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut parsed = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(desktop_file) = desktop_files.get(i) else {
+                                break parsed;
+                            };
+                            parsed.push((i, Self::parse(desktop_file, locales)));
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect::<Vec<(usize, Option<Self>)>>()
+        });
+        parsed.sort_unstable_by_key(|(i, _)| *i);
+        parsed.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    fn parse(desktop_file: &DesktopFile, locales: &[String]) -> Option<Self> {
+        let path = desktop_file.path.to_str()?;
         let content = match fs::read_to_string(path) {
             Ok(content) => content,
             Err(_) => return None,
         };
-        let ini = match Ini::load_from_str(&content) {
+        // quoting and escapes are handled per the desktop entry spec instead
+        let option = ParseOption {
+            enabled_quote: false,
+            enabled_escape: false,
+            ..ParseOption::default()
+        };
+        let ini = match Ini::load_from_str_opt(&content, option) {
             Ok(ini) => ini,
             Err(_) => return None,
         };
-        let section = ini.section(Some("Desktop Entry"));
-        if let Some(section) = section {
-            let name = section.get("Name")?;
-            let exec = section.get("Exec")?;
-            let terminal = match section.get("Terminal") {
-                Some("True") | Some("true") => true,
-                Some("False") | Some("false") => false,
-                Some(_) => {
-                    return None;
-                }
-                None => {
-                    return None;
-                }
-            };
-            let categories = match section.get("Categories") {
-                Some(categories) => categories
-                    .split(';')
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>(),
-                None => Vec::new(),
-            };
-            let comment = section.get("Comment").map(|comment| comment.to_string());
-            let icon = Self::set_icon(name, categories).clone();
-            let exec_split = exec
-                .split_whitespace()
-                .filter(|e| !e.starts_with('%'))
-                .map(|e| e.to_string())
-                .collect::<Vec<String>>();
-            let filename_ref = exec_split.first()?;
-            let filename = CString::new(filename_ref.to_string()).unwrap();
-            let mut args = Vec::new();
-            if exec_split.len() > 1 {
-                args = exec_split[1..]
-                    .iter()
-                    .map(|a| CString::new(a.to_string()).unwrap())
-                    .collect::<Vec<CString>>();
-            }
-            let db_entry = db
-                .entries
+        let section = ini.section(Some("Desktop Entry"))?;
+        if section
+            .get("Type")
+            .is_some_and(|kind| kind != "Application")
+        {
+            return None;
+        }
+        let untranslated_name = unescape(section.get("Name")?);
+        let name = get_localized(section, "Name", locales)?;
+        let generic_name = get_localized(section, "GenericName", locales);
+        let exec = unescape(section.get("Exec")?);
+        let terminal = parse_bool(section.get("Terminal"))?;
+        let categories = get_list(section, "Categories");
+        let keywords = get_localized_list(section, "Keywords", locales);
+        let comment = get_localized(section, "Comment", locales);
+        let desktop_icon = section.get("Icon").map(unescape);
+        let context = ExecContext {
+            name: &name,
+            path,
+            icon: desktop_icon.as_deref(),
+        };
+        let exec = Exec::parse(&exec, &context)?;
+        let actions = get_list(section, "Actions")
+            .iter()
+            .filter_map(|action| {
+                let section = ini.section(Some(format!("Desktop Action {}", action)))?;
+                Action::from_section(section, &context, locales)
+            })
+            .collect::<Vec<Action>>();
+        let no_display = parse_bool(section.get("NoDisplay")) == Some(true)
+            || parse_bool(section.get("Hidden")) == Some(true);
+
+        Some(Self {
+            id: desktop_file.id.clone(),
+            name,
+            untranslated_name,
+            generic_name,
+            keywords,
+            categories,
+            exec,
+            terminal,
+            comment,
+            actions,
+            no_display,
+            only_show_in: get_list(section, "OnlyShowIn"),
+            not_show_in: get_list(section, "NotShowIn"),
+            try_exec: section.get("TryExec").map(unescape),
+        })
+    }
+
+    /// Applies `NoDisplay`, `Hidden`, `OnlyShowIn`, `NotShowIn` and `TryExec`.
+    fn is_visible(&self) -> bool {
+        if self.no_display {
+            return false;
+        }
+        let current_desktops = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        let current_desktops = current_desktops
+            .split(':')
+            .filter(|desktop| !desktop.is_empty())
+            .collect::<Vec<&str>>();
+        if !self.only_show_in.is_empty()
+            && !self
+                .only_show_in
                 .iter()
-                .find(|entry| entry.name == name)
-                .cloned()
-                .unwrap_or(DbEntry::new(name));
-            let name = name.to_string();
-
-            return Some(Self {
-                name,
-                filename,
-                args,
-                terminal,
-                comment,
-                icon,
-                db_entry,
-            });
-        }
-        None
-    }
-
-    pub fn find_all() -> Vec<Self> {
-        let mut applications = Vec::new();
+                .any(|desktop| current_desktops.contains(&desktop.as_str()))
+        {
+            return false;
+        }
+        if self
+            .not_show_in
+            .iter()
+            .any(|desktop| current_desktops.contains(&desktop.as_str()))
+        {
+            return false;
+        }
+        match &self.try_exec {
+            Some(try_exec) => is_executable(try_exec),
+            None => true,
+        }
+    }
+}
+
+impl Application {
+    pub fn from_entry(entry: DesktopEntry, db: &Db) -> Self {
+        let hidden = !entry.is_visible();
+        let icon = Self::set_icon(&entry.untranslated_name, &entry.categories).clone();
+        let db_entry = Self::find_db_entry(db, &entry.untranslated_name);
+        Self {
+            id: entry.id,
+            name: entry.name,
+            untranslated_name: entry.untranslated_name,
+            generic_name: entry.generic_name,
+            keywords: entry.keywords,
+            categories: entry.categories,
+            exec: entry.exec,
+            terminal: entry.terminal,
+            hidden,
+            comment: entry.comment,
+            icon,
+            db_entry,
+            actions: entry.actions,
+            parent: None,
+        }
+    }
+
+    /// A plain dmenu item, identified by its line index.
+    pub fn from_item(index: usize, item: String) -> Self {
+        Self {
+            id: index.to_string(),
+            untranslated_name: item.clone(),
+            db_entry: DbEntry::new(item.as_str()),
+            name: item,
+            generic_name: None,
+            keywords: Vec::new(),
+            categories: Vec::new(),
+            exec: Exec {
+                program: String::new(),
+                args: Vec::new(),
+            },
+            terminal: false,
+            hidden: false,
+            comment: None,
+            icon: Icon::EMPTY,
+            actions: Vec::new(),
+            parent: None,
+        }
+    }
+
+    pub fn find_all(desktop_files: &[DesktopFile], config: &ApplicationListConfig) -> Vec<Self> {
         let db = Db::load();
-        let home = home_dir().unwrap();
-        let dirs = vec![
-            String::from("/usr/share/applications/"),
-            format!("{}/.local/share/applications/", home.display()),
-        ];
-        for dir in dirs {
-            let path = Path::new(&dir);
-            if path.exists() && path.is_dir() {
-                for entry in fs::read_dir(path).expect("unable to read target directory") {
-                    let entry = entry.expect("unable to read entry");
-                    let path = entry.path();
-                    if path.is_file()
-                        && path.extension().and_then(|s| s.to_str()) == Some("desktop")
-                    {
-                        match Application::from_file(path.to_str().unwrap(), &db) {
-                            Some(app) => applications.push(app),
-                            None => continue,
-                        }
-                    }
+        let locales = Locale::from_env().candidates();
+        let entries = DesktopEntry::parse_all(desktop_files, &locales)
+            .into_iter()
+            .flatten()
+            .collect();
+        let mut applications = Self::from_entries(entries, config, &db);
+        Self::sort(&mut applications, config);
+        applications
+    }
+
+    /// Turns parsed entries into visible applications and their actions, unsorted.
+    pub fn from_entries(
+        entries: Vec<DesktopEntry>,
+        config: &ApplicationListConfig,
+        db: &Db,
+    ) -> Vec<Self> {
+        let mut applications = entries
+            .into_iter()
+            .map(|entry| Application::from_entry(entry, db))
+            .filter(|application| config.show_hidden || !application.hidden)
+            .collect::<Vec<Self>>();
+        if !matches!(config.actions, ApplicationListActions::Hidden) {
+            let actions = applications
+                .iter()
+                .flat_map(|application| application.action_entries(db))
+                .collect::<Vec<Self>>();
+            applications.extend(actions);
+        }
+        applications
+    }
+
+    /// Orders applications by `config.order`, grouping actions below their parent if enabled.
+    pub fn sort(applications: &mut Vec<Self>, config: &ApplicationListConfig) {
+        let now = DbEntry::now();
+        let by_name = |a: &Self, b: &Self| {
+            a.name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then(a.id.cmp(&b.id))
+        };
+        match config.order {
+            ApplicationListOrder::Alphabetical => applications.sort_by(by_name),
+            ApplicationListOrder::MostUsed => applications.sort_by(|a, b| {
+                b.db_entry
+                    .launch_count
+                    .cmp(&a.db_entry.launch_count)
+                    .then_with(|| by_name(a, b))
+            }),
+            ApplicationListOrder::RecentlyUsed => applications.sort_by(|a, b| {
+                b.db_entry
+                    .last_launched
+                    .cmp(&a.db_entry.last_launched)
+                    .then_with(|| by_name(a, b))
+            }),
+            ApplicationListOrder::Frecency | ApplicationListOrder::MatchScore => applications
+                .sort_by(|a, b| {
+                    b.db_entry
+                        .frecency_at(now)
+                        .total_cmp(&a.db_entry.frecency_at(now))
+                        .then(b.db_entry.launch_count.cmp(&a.db_entry.launch_count))
+                        .then_with(|| by_name(a, b))
+                }),
+        }
+        if matches!(config.actions, ApplicationListActions::Grouped) {
+            *applications = Self::group_actions(std::mem::take(applications));
+        }
+    }
+
+    /// Turns each action into a launchable entry named like `Firefox: New Private Window`.
+    fn action_entries(&self, db: &Db) -> Vec<Self> {
+        self.actions
+            .iter()
+            .map(|action| {
+                let untranslated_name =
+                    format!("{}: {}", self.untranslated_name, action.untranslated_name);
+                Self {
+                    name: format!("{}: {}", self.name, action.name),
+                    db_entry: Self::find_db_entry(db, &untranslated_name),
+                    untranslated_name,
+                    exec: action.exec.clone(),
+                    actions: Vec::new(),
+                    parent: Some(self.id.clone()),
+                    ..self.clone()
                 }
+            })
+            .collect()
+    }
+
+    /// Moves every action entry directly below its parent, keeping the relative order.
+    fn group_actions(applications: Vec<Self>) -> Vec<Self> {
+        let mut actions: HashMap<String, Vec<Self>> = HashMap::new();
+        let mut parents = Vec::new();
+        for application in applications {
+            match &application.parent {
+                Some(parent) => actions.entry(parent.clone()).or_default().push(application),
+                None => parents.push(application),
             }
         }
-        applications.sort_by(|a, b| {
-            b.db_entry
-                .launch_count
-                .cmp(&a.db_entry.launch_count)
-                .then(a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-        });
-        applications
+        let mut grouped = Vec::new();
+        for parent in parents {
+            let children = actions.remove(&parent.id).unwrap_or_default();
+            grouped.push(parent);
+            grouped.extend(children);
+        }
+        grouped
     }
 
-    pub fn get_icon(&self) -> Span<'_> {
-        Span::styled(
-            format!("{}  ", self.icon.str),
-            Style::new().fg(self.icon.color),
-        )
+    fn find_db_entry(db: &Db, name: &str) -> DbEntry {
+        db.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .cloned()
+            .unwrap_or(DbEntry::new(name))
     }
 
-    pub fn get_highlighted_name(&self, filter: &str) -> Vec<Span<'_>> {
-        let mut spans = Vec::new();
+    pub fn get_icon(&self, colored: bool) -> Span<'_> {
+        let style = if colored {
+            Style::new().fg(self.icon.color)
+        } else {
+            Style::new()
+        };
+        Span::styled(format!("{}  ", self.icon.str), style)
+    }
+
+    pub fn get_highlighted_name(
+        &self,
+        filter: &str,
+        config: &SearchConfig,
+        highlight_style: Style,
+    ) -> Vec<Span<'_>> {
         let name = &self.name;
-        let indices = name
-            .to_lowercase()
-            .match_indices(&filter.to_lowercase())
-            .map(|(index, _)| index)
-            .collect::<Vec<usize>>();
-        if filter.is_empty() || indices.is_empty() {
-            spans.push(Span::raw(name));
-            return spans;
-        }
-        if indices[0] > 0 {
-            spans.push(Span::raw(&name[..indices[0]]));
-        }
-        let mut iteration = 0;
-        let bg_color = Color::DarkGray;
-        for index in indices.iter() {
-            let start = *index;
-            let end = start + filter.len();
-            spans.push(Span::raw(&name[start..end]).style(Style::new().bold().bg(bg_color)));
-            let next_index: usize = if iteration < indices.len() - 1 {
-                iteration += 1;
-                indices[iteration]
-            } else {
-                name.len()
-            };
-            spans.push(Span::raw(&name[end..next_index]));
+        let query = search::Query::new(filter, config);
+        let positions = match search::match_positions(name, &query, config) {
+            Some(positions) if !positions.is_empty() => positions,
+            _ => return vec![Span::raw(name)],
+        };
+        let mut spans = Vec::new();
+        let mut run_start = 0;
+        let mut run_highlighted = false;
+        let mut positions = positions.iter().peekable();
+        for (grapheme_index, (byte_index, _)) in name.grapheme_indices(true).enumerate() {
+            let highlighted = positions.next_if_eq(&&grapheme_index).is_some();
+            if highlighted != run_highlighted && byte_index > run_start {
+                let span = Span::raw(&name[run_start..byte_index]);
+                spans.push(if run_highlighted {
+                    span.style(highlight_style)
+                } else {
+                    span
+                });
+                run_start = byte_index;
+            }
+            run_highlighted = highlighted;
         }
+        let span = Span::raw(&name[run_start..]);
+        spans.push(if run_highlighted {
+            span.style(highlight_style)
+        } else {
+            span
+        });
         spans
     }
 
-    fn set_icon(name: &str, categories: Vec<String>) -> &Icon {
+    fn set_icon<'a>(name: &str, categories: &[String]) -> &'a Icon {
         if let Some(application_icon) = APPLICATION_ICON_MAP.get(name) {
             return application_icon;
         }
@@ -173,3 +453,123 @@ impl Application {
         &Icon::EMPTY
     }
 }
+
+/// Parses a boolean value, treating a missing key as `false`.
+fn parse_bool(value: Option<&str>) -> Option<bool> {
+    match value {
+        Some("True") | Some("true") => Some(true),
+        Some("False") | Some("false") | None => Some(false),
+        Some(_) => None,
+    }
+}
+
+/// Looks up `key[locale]` for each locale candidate before falling back to the bare `key`.
+fn get_localized(section: &Properties, key: &str, locales: &[String]) -> Option<String> {
+    locales
+        .iter()
+        .find_map(|locale| section.get(format!("{}[{}]", key, locale)))
+        .or_else(|| section.get(key))
+        .map(unescape)
+}
+
+/// Splits a `;` separated list value.
+fn get_list(section: &Properties, key: &str) -> Vec<String> {
+    split_list(section.get(key))
+}
+
+/// Like [`get_list`], but resolves `key[locale]` first.
+fn get_localized_list(section: &Properties, key: &str, locales: &[String]) -> Vec<String> {
+    let list = locales
+        .iter()
+        .find_map(|locale| section.get(format!("{}[{}]", key, locale)))
+        .or_else(|| section.get(key));
+    split_list(list)
+}
+
+fn split_list(list: Option<&str>) -> Vec<String> {
+    match list {
+        Some(list) => list
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .map(unescape)
+            .collect::<Vec<String>>(),
+        None => Vec::new(),
+    }
+}
+
+/// Checks whether `program` is an executable file, searching `$PATH` for bare names.
+pub fn is_executable(program: &str) -> bool {
+    let is_executable_file = |path: &Path| {
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+    if program.contains('/') {
+        return is_executable_file(Path::new(program));
+    }
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&path).any(|dir| is_executable_file(&dir.join(program)))
+}
+
+/// Resolves the string level escapes `\s`, `\n`, `\t`, `\r` and `\\` of a desktop entry value,
+/// leaving any other backslash in place for the `Exec` tokenizer.
+pub fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let escaped = match chars.peek() {
+            Some('s') => ' ',
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('\\') => '\\',
+            _ => {
+                unescaped.push('\\');
+                continue;
+            }
+        };
+        chars.next();
+        unescaped.push(escaped);
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::Theme;
+
+    fn highlighted(name: &str, filter: &str) -> Vec<(String, bool)> {
+        let application = Application::from_item(0, name.to_string());
+        application
+            .get_highlighted_name(filter, &SearchConfig::default(), Theme::default().highlight)
+            .into_iter()
+            .map(|span| (span.content.to_string(), span.style.bg.is_some()))
+            .collect()
+    }
+
+    #[test]
+    fn highlights_whole_graphemes() {
+        assert_eq!(
+            highlighted("İstanbul", "stan"),
+            [
+                ("İ".into(), false),
+                ("stan".into(), true),
+                ("bul".into(), false)
+            ]
+        );
+        assert_eq!(
+            highlighted("Cafe\u{301} 日本", "日本"),
+            [("Cafe\u{301} ".into(), false), ("日本".into(), true)]
+        );
+        assert_eq!(
+            highlighted("👩‍💻 Dev", "dev"),
+            [("👩‍💻 ".into(), false), ("Dev".into(), true)]
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use std::env;
+
+/// Command line arguments.
+#[derive(Debug, Default)]
+pub struct Args {
+    /// read items from stdin and print the selection to stdout
+    pub dmenu: bool,
+    pub prompt: Option<String>,
+    /// print the line number of the selection instead of the item
+    pub index: bool,
+    /// ignore the parsed-entry cache and rebuild it from scratch
+    pub rebuild_cache: bool,
+}
+
+impl Args {
+    pub const USAGE: &str =
+        "usage: spellbook [--dmenu [--index]] [-p | --prompt <prompt>] [--rebuild-cache]";
+
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Self::default();
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--dmenu" => args.dmenu = true,
+                "--index" => args.index = true,
+                "--rebuild-cache" => args.rebuild_cache = true,
+                "-p" | "--prompt" => {
+                    let prompt = iter.next().ok_or(format!("missing value for '{}'", arg))?;
+                    args.prompt = Some(prompt);
+                }
+                "-h" | "--help" => return Err(String::new()),
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+        if args.index && !args.dmenu {
+            return Err(String::from("'--index' requires '--dmenu'"));
+        }
+        Ok(args)
+    }
+}
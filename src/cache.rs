@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    application::DesktopEntry,
+    xdg::{self, DesktopFile},
+};
+
+/// Parsed desktop entries, kept in `$XDG_CACHE_HOME/spellbook/entries.bin`.
+///
+/// Files are only re-parsed when their mtime or size changed, and the
+/// application directories are only rescanned when one of their mtimes changed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    version: u32,
+    /// locale candidates the entries were parsed for
+    locales: Vec<String>,
+    /// application directories the cache was scanned from
+    roots: Vec<PathBuf>,
+    /// every directory searched for desktop files, to notice additions and removals
+    dirs: Vec<CachedDir>,
+    files: Vec<CachedFile>,
+    /// index into `files` by path
+    #[serde(skip)]
+    index: HashMap<PathBuf, usize>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedDir {
+    path: PathBuf,
+    /// `None` if the directory does not exist
+    mtime: Option<SystemTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedFile {
+    id: String,
+    path: PathBuf,
+    mtime: Option<SystemTime>,
+    size: u64,
+    /// `None` if the file is not a valid application entry
+    entry: Option<DesktopEntry>,
+}
+
+impl Cache {
+    /// Bumped whenever the cached format changes.
+    const VERSION: u32 = 1;
+    const DIR: &str = "spellbook";
+    const FILENAME: &str = "entries.bin";
+
+    /// Loads the cache, starting over if `rebuild` is set or it was built for other locales.
+    pub fn load(rebuild: bool, locales: &[String]) -> Self {
+        let cache = fs::read(Self::path())
+            .ok()
+            .filter(|_| !rebuild)
+            .and_then(|bytes| bincode::deserialize::<Self>(&bytes).ok())
+            .filter(|cache| cache.version == Self::VERSION && cache.locales == locales);
+        match cache {
+            Some(mut cache) => {
+                cache.reindex();
+                cache
+            }
+            None => Self {
+                version: Self::VERSION,
+                locales: locales.to_vec(),
+                changed: true,
+                ..Self::default()
+            },
+        }
+    }
+
+    pub fn save(&self) {
+        if !self.changed {
+            return;
+        }
+        let Ok(bytes) = bincode::serialize(self) else {
+            return;
+        };
+        let path = Self::path();
+        // written to a temporary file first, so a concurrent load never sees half a cache
+        let temporary = path.with_extension("bin.tmp");
+        let written = fs::create_dir_all(xdg::cache_home().join(Self::DIR))
+            .and_then(|_| fs::File::create(&temporary))
+            .and_then(|mut file| file.write_all(&bytes))
+            .and_then(|_| fs::rename(&temporary, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+
+    fn path() -> PathBuf {
+        xdg::cache_home().join(Self::DIR).join(Self::FILENAME)
+    }
+
+    fn reindex(&mut self) {
+        self.index = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (file.path.clone(), i))
+            .collect();
+    }
+
+    /// Desktop files of the application directories, rescanning them only if one changed.
+    pub fn desktop_files(&mut self) -> Vec<DesktopFile> {
+        let unchanged = self.roots == xdg::application_dirs()
+            && self.dirs.iter().all(|dir| mtime(&dir.path) == dir.mtime);
+        if !unchanged {
+            self.rescan();
+        }
+        self.files
+            .iter()
+            .map(|file| DesktopFile {
+                id: file.id.clone(),
+                path: file.path.clone(),
+            })
+            .collect()
+    }
+
+    fn rescan(&mut self) {
+        self.roots = xdg::application_dirs();
+        let (desktop_files, dirs) = xdg::scan();
+        let mut files = std::mem::take(&mut self.files)
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect::<HashMap<PathBuf, CachedFile>>();
+        self.files = desktop_files
+            .into_iter()
+            .map(|desktop_file| match files.remove(&desktop_file.path) {
+                Some(file) => CachedFile {
+                    id: desktop_file.id,
+                    ..file
+                },
+                None => CachedFile {
+                    id: desktop_file.id,
+                    path: desktop_file.path,
+                    mtime: None,
+                    size: 0,
+                    entry: None,
+                },
+            })
+            .collect();
+        self.dirs = dirs
+            .into_iter()
+            .map(|path| CachedDir {
+                mtime: mtime(&path),
+                path,
+            })
+            .collect();
+        self.reindex();
+        self.changed = true;
+    }
+
+    /// Parsed entries of `desktop_files`, re-parsing only files that changed.
+    pub fn entries(&mut self, desktop_files: &[DesktopFile]) -> Vec<DesktopEntry> {
+        let indices = desktop_files
+            .iter()
+            .map(|desktop_file| self.index.get(&desktop_file.path).copied())
+            .collect::<Vec<Option<usize>>>();
+        let mut stale = Vec::new();
+        for (desktop_file, &i) in desktop_files.iter().zip(&indices) {
+            let Some(i) = i else {
+                continue;
+            };
+            let metadata = fs::metadata(&desktop_file.path).ok();
+            let current_mtime = metadata.as_ref().and_then(|m| m.modified().ok());
+            let size = metadata.as_ref().map_or(0, |m| m.len());
+            let file = &mut self.files[i];
+            if file.mtime.is_none() || file.mtime != current_mtime || file.size != size {
+                file.mtime = current_mtime;
+                file.size = size;
+                stale.push((i, desktop_file.clone()));
+            }
+        }
+        if !stale.is_empty() {
+            let (stale_indices, stale_files): (Vec<usize>, Vec<DesktopFile>) =
+                stale.into_iter().unzip();
+            let parsed = DesktopEntry::parse_all(&stale_files, &self.locales);
+            for (i, entry) in stale_indices.into_iter().zip(parsed) {
+                self.files[i].entry = entry;
+            }
+            self.changed = true;
+        }
+        desktop_files
+            .iter()
+            .zip(indices)
+            .filter_map(|(desktop_file, i)| {
+                // the ID depends on the application directory the file was found in
+                let mut entry = self.files[i?].entry.clone()?;
+                entry.id = desktop_file.id.clone();
+                Some(entry)
+            })
+            .collect()
+    }
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
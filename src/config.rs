@@ -1,6 +1,9 @@
-use std::{env, fs};
+use std::{env, fs, str::FromStr};
 
-use serde::{Deserialize, Serialize};
+use ratatui::style::{Color, Modifier};
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+use crate::{keybindings::Keybindings, tui};
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
@@ -10,7 +13,12 @@ pub struct Config {
     pub margin: MarginConfig,
     pub application_list: ApplicationListConfig,
     pub scrollbar: ScrollbarConfig,
+    pub mouse: MouseConfig,
     pub info: InfoConfig,
+    pub search: SearchConfig,
+    pub terminal: TerminalConfig,
+    pub keybindings: Keybindings,
+    pub theme: ThemeConfig,
 }
 
 impl Config {
@@ -18,14 +26,19 @@ impl Config {
     const FILENAME: &str = "spellbook.toml";
 
     pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|error| {
+            tui::restore();
+            panic!("{}", error);
+        })
+    }
+
+    /// Like [`Config::load`], but returns invalid configs as an error instead of panicking.
+    pub fn try_load() -> Result<Self, toml::de::Error> {
         let path = Self::get_full_path();
         let Ok(toml) = fs::read_to_string(&path) else {
-            return Self::default();
+            return Ok(Self::default());
         };
-        toml::from_str::<Self>(&toml).unwrap_or_else(|error| {
-            ratatui::restore();
-            panic!("{}", error);
-        })
+        toml::from_str::<Self>(&toml)
     }
 
     fn get_path() -> String {
@@ -64,14 +77,29 @@ pub struct MarginConfig {
 pub struct ApplicationListConfig {
     pub display_icons: bool,
     pub order: ApplicationListOrder,
+    pub show_hidden: bool,
+    pub actions: ApplicationListActions,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum ApplicationListActions {
+    Hidden,
+    #[default]
+    Flat,
+    Grouped,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub enum ApplicationListOrder {
     Alphabetical,
-    #[default]
     MostUsed,
+    RecentlyUsed,
+    #[default]
+    Frecency,
+    /// same as frecency, since every order ranks by relevance while the filter is non-empty
+    MatchScore,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -80,8 +108,178 @@ pub struct ScrollbarConfig {
     pub enable: bool,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MouseConfig {
+    /// captures the mouse, which disables the terminal's own text selection
+    pub enable: bool,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct InfoConfig {
     pub enable: bool,
 }
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    pub mode: SearchMode,
+    /// match regardless of diacritics and romanize kana and Hangul, e.g. `ecran` matches `Écran`
+    /// and `kana` matches `カナ`; Chinese characters are not romanized
+    pub normalize: bool,
+    pub weights: SearchWeights,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum SearchMode {
+    Exact,
+    Substring,
+    #[default]
+    Fuzzy,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchWeights {
+    pub name: u32,
+    pub generic_name: u32,
+    pub keywords: u32,
+    pub exec: u32,
+    pub categories: u32,
+    pub comment: u32,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            name: 100,
+            generic_name: 50,
+            keywords: 40,
+            exec: 30,
+            categories: 20,
+            comment: 10,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerminalConfig {
+    /// e.g. `kitty --class "float term" {cmd}`, quoted like the `Exec` key of a desktop entry
+    pub command: String,
+    pub mode: TerminalMode,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum TerminalMode {
+    /// runs terminal applications in place of spellbook
+    #[default]
+    Replace,
+    /// opens terminal applications in a new terminal emulator window
+    Spawn,
+}
+
+/// Styles of every widget, each patched over the built-in style.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub mode: ThemeMode,
+    pub list: StyleConfig,
+    pub selected: StyleConfig,
+    /// matched parts of application names
+    pub highlight: StyleConfig,
+    pub placeholder: StyleConfig,
+    pub counter: StyleConfig,
+    pub info: StyleConfig,
+    pub border: StyleConfig,
+    pub border_type: BorderType,
+    pub scrollbar: StyleConfig,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum ThemeMode {
+    /// picks the light or dark palette from the terminal's background color
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+/// Colors are ANSI names like `light_blue`, indices like `208` or RGB like `#ff8700`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleConfig {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub fg: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub bg: Option<Color>,
+    /// replaces the built-in modifiers if set
+    pub modifiers: Option<Vec<StyleModifier>>,
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawColor {
+        Index(u8),
+        Name(String),
+    }
+    let color = match RawColor::deserialize(deserializer)? {
+        RawColor::Index(index) => Color::Indexed(index),
+        RawColor::Name(name) => Color::from_str(&name).map_err(|_| {
+            de::Error::custom(format!(
+                "invalid color `{}`, expected a name like `light_blue`, an index like `208` or `#rrggbb`",
+                name
+            ))
+        })?,
+    };
+    Ok(Some(color))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum StyleModifier {
+    Bold,
+    Dim,
+    Italic,
+    Underlined,
+    SlowBlink,
+    RapidBlink,
+    Reversed,
+    Hidden,
+    CrossedOut,
+}
+
+impl From<StyleModifier> for Modifier {
+    fn from(modifier: StyleModifier) -> Self {
+        match modifier {
+            StyleModifier::Bold => Modifier::BOLD,
+            StyleModifier::Dim => Modifier::DIM,
+            StyleModifier::Italic => Modifier::ITALIC,
+            StyleModifier::Underlined => Modifier::UNDERLINED,
+            StyleModifier::SlowBlink => Modifier::SLOW_BLINK,
+            StyleModifier::RapidBlink => Modifier::RAPID_BLINK,
+            StyleModifier::Reversed => Modifier::REVERSED,
+            StyleModifier::Hidden => Modifier::HIDDEN,
+            StyleModifier::CrossedOut => Modifier::CROSSED_OUT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum BorderType {
+    Plain,
+    Rounded,
+    Double,
+    Thick,
+    #[default]
+    ProportionalWide,
+    ProportionalTall,
+}
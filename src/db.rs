@@ -1,4 +1,8 @@
-use std::{env::home_dir, io::Write};
+use std::{
+    env::home_dir,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -55,9 +59,16 @@ pub struct DbEntry {
     pub name: String,
     pub launch_count: usize,
     pub blacklisted: bool,
+    /// exponentially decayed launch count as of `last_launched`
+    pub frecency: f64,
+    /// unix timestamp in seconds
+    pub last_launched: u64,
 }
 
 impl DbEntry {
+    /// time after which a launch only counts half as much
+    const FRECENCY_HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
     pub fn new<S: Into<String>>(name: S) -> Self {
         let name = name.into();
         Self {
@@ -65,4 +76,28 @@ impl DbEntry {
             ..Default::default()
         }
     }
+
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+
+    pub fn record_launch(&mut self) {
+        let now = Self::now();
+        self.frecency = self.frecency_at(now) + 1.0;
+        self.last_launched = now;
+        self.launch_count += 1;
+    }
+
+    pub fn frecency_at(&self, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(self.last_launched) as f64;
+        self.frecency * 0.5f64.powf(elapsed / Self::FRECENCY_HALF_LIFE_SECS)
+    }
+
+    /// Entries that were never launched nor blacklisted carry no information.
+    pub fn is_empty(&self) -> bool {
+        self.launch_count == 0 && !self.blacklisted
+    }
 }
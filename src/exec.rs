@@ -0,0 +1,366 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+/// Tokenized `Exec` key of a desktop entry.
+///
+/// `%c`, `%k` and `%i` are expanded while parsing, file and url field codes
+/// are kept as placeholders so targets can be filled in at launch time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Exec {
+    pub program: String,
+    pub args: Vec<ExecArg>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ExecArg {
+    Literal(String),
+    /// `%f` or `%u`, optionally embedded in surrounding text
+    Target {
+        kind: TargetKind,
+        prefix: String,
+        suffix: String,
+    },
+    /// `%F` or `%U`
+    Targets(TargetKind),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetKind {
+    File,
+    Url,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FieldCode {
+    Target(TargetKind),
+    Targets(TargetKind),
+    Icon,
+}
+
+#[derive(Debug, Default)]
+struct Token {
+    prefix: String,
+    field: Option<FieldCode>,
+    suffix: String,
+    quoted: bool,
+}
+
+impl Token {
+    fn push(&mut self, c: char) {
+        match self.field {
+            Some(_) => self.suffix.push(c),
+            None => self.prefix.push(c),
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        match self.field {
+            Some(_) => self.suffix.push_str(s),
+            None => self.prefix.push_str(s),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.quoted && self.field.is_none() && self.prefix.is_empty()
+    }
+}
+
+/// Values available for `%c`, `%k` and `%i` expansion.
+pub struct ExecContext<'a> {
+    pub name: &'a str,
+    pub path: &'a str,
+    pub icon: Option<&'a str>,
+}
+
+impl Exec {
+    /// Parses an `Exec` value that has already been unescaped on the string level.
+    pub fn parse(value: &str, context: &ExecContext) -> Option<Self> {
+        let mut args = Vec::new();
+        for token in Self::tokenize(value, Some(context))? {
+            Self::finish_token(token, context, &mut args);
+        }
+
+        let Some(ExecArg::Literal(program)) = args.first() else {
+            return None;
+        };
+        if program.is_empty() {
+            return None;
+        }
+        let program = program.clone();
+        args.remove(0);
+        Some(Self { program, args })
+    }
+
+    /// Splits a command line into arguments, with the quoting rules of `Exec` but no field codes.
+    pub fn split(value: &str) -> Option<Vec<String>> {
+        let tokens = Self::tokenize(value, None)?;
+        Some(tokens.into_iter().map(|token| token.prefix).collect())
+    }
+
+    /// Expands field codes only if a `context` is given, otherwise `%` is an ordinary character.
+    fn tokenize(value: &str, context: Option<&ExecContext>) -> Option<Vec<Token>> {
+        if value.contains('\0') {
+            return None;
+        }
+        let mut tokens = Vec::new();
+        let mut token = Token::default();
+        let mut chars = value.chars().peekable();
+        let mut in_quotes = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    in_quotes = !in_quotes;
+                    token.quoted = true;
+                }
+                '\\' if in_quotes => match chars.peek() {
+                    Some('"' | '`' | '$' | '\\') => token.push(chars.next()?),
+                    _ => token.push('\\'),
+                },
+                '\\' => token.push(chars.next()?),
+                ' ' | '\t' | '\n' if !in_quotes => {
+                    let token = std::mem::take(&mut token);
+                    if !token.is_empty() {
+                        tokens.push(token);
+                    }
+                }
+                '%' if in_quotes && context.is_some() => {
+                    if chars.peek() == Some(&'%') {
+                        chars.next();
+                    }
+                    token.push('%');
+                }
+                '%' => {
+                    let Some(context) = context else {
+                        token.push('%');
+                        continue;
+                    };
+                    let field = match chars.next()? {
+                        '%' => {
+                            token.push('%');
+                            continue;
+                        }
+                        'c' => {
+                            token.push_str(context.name);
+                            continue;
+                        }
+                        'k' => {
+                            token.push_str(context.path);
+                            continue;
+                        }
+                        'f' => FieldCode::Target(TargetKind::File),
+                        'u' => FieldCode::Target(TargetKind::Url),
+                        'F' => FieldCode::Targets(TargetKind::File),
+                        'U' => FieldCode::Targets(TargetKind::Url),
+                        'i' => FieldCode::Icon,
+                        // deprecated (%d, %D, %n, %N, %v, %m) and unknown codes are dropped
+                        _ => continue,
+                    };
+                    if token.field.is_none() {
+                        token.field = Some(field);
+                    }
+                }
+                c => token.push(c),
+            }
+        }
+        if in_quotes {
+            return None;
+        }
+        if !token.is_empty() {
+            tokens.push(token);
+        }
+        Some(tokens)
+    }
+
+    fn finish_token(token: Token, context: &ExecContext, args: &mut Vec<ExecArg>) {
+        let Token {
+            prefix,
+            field,
+            suffix,
+            ..
+        } = token;
+        let standalone = prefix.is_empty() && suffix.is_empty();
+        let arg = match field {
+            None => ExecArg::Literal(prefix),
+            Some(FieldCode::Targets(kind)) if standalone => ExecArg::Targets(kind),
+            Some(FieldCode::Target(kind) | FieldCode::Targets(kind)) => ExecArg::Target {
+                kind,
+                prefix,
+                suffix,
+            },
+            Some(FieldCode::Icon) => match context.icon {
+                Some(icon) if standalone => {
+                    args.push(ExecArg::Literal(String::from("--icon")));
+                    ExecArg::Literal(icon.to_string())
+                }
+                None if standalone => return,
+                icon => {
+                    ExecArg::Literal(format!("{}{}{}", prefix, icon.unwrap_or_default(), suffix))
+                }
+            },
+        };
+        args.push(arg);
+    }
+
+    /// The file name of the program, e.g. `code` for `/usr/bin/code`.
+    pub fn basename(&self) -> &str {
+        self.program.rsplit('/').next().unwrap_or(&self.program)
+    }
+
+    /// Builds the argument vector, including the program as `argv[0]`.
+    pub fn argv(&self, targets: &[String]) -> Vec<CString> {
+        let mut argv = vec![self.program.clone()];
+        for arg in &self.args {
+            match arg {
+                ExecArg::Literal(literal) => argv.push(literal.clone()),
+                ExecArg::Target {
+                    kind,
+                    prefix,
+                    suffix,
+                } => match targets.first() {
+                    Some(target) => {
+                        argv.push(format!("{}{}{}", prefix, kind.convert(target), suffix))
+                    }
+                    None if prefix.is_empty() && suffix.is_empty() => {}
+                    None => argv.push(format!("{}{}", prefix, suffix)),
+                },
+                ExecArg::Targets(kind) => {
+                    argv.extend(targets.iter().map(|target| kind.convert(target)))
+                }
+            }
+        }
+        argv.into_iter()
+            .filter_map(|arg| CString::new(arg).ok())
+            .collect()
+    }
+}
+
+impl TargetKind {
+    /// Converts a target between a local path and a `file://` url as required by the field code.
+    fn convert(self, target: &str) -> String {
+        const FILE_SCHEME: &str = "file://";
+        match self {
+            TargetKind::File => target
+                .strip_prefix(FILE_SCHEME)
+                .unwrap_or(target)
+                .to_string(),
+            TargetKind::Url if target.starts_with('/') => format!("{}{}", FILE_SCHEME, target),
+            TargetKind::Url => target.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::unescape;
+
+    /// Parses a raw `Exec` value as found in a desktop file and builds its argv.
+    fn argv(value: &str, icon: Option<&str>, targets: &[&str]) -> Option<Vec<String>> {
+        let context = ExecContext {
+            name: "Foo Viewer",
+            path: "/usr/share/applications/foo.desktop",
+            icon,
+        };
+        let exec = Exec::parse(&unescape(value), &context)?;
+        let targets = targets
+            .iter()
+            .map(|target| target.to_string())
+            .collect::<Vec<String>>();
+        Some(
+            exec.argv(&targets)
+                .into_iter()
+                .map(|arg| arg.into_string().unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn resolves_both_levels_of_escaping() {
+        assert_eq!(
+            argv(r#"sh -c "echo \\$HOME \\"hi\\" \\\\ \\`x\\`""#, None, &[]).unwrap(),
+            ["sh", "-c", r#"echo $HOME "hi" \ `x`"#]
+        );
+        assert_eq!(
+            argv(r#"foo "a\sb" c\sd"#, None, &[]).unwrap(),
+            ["foo", "a b", "c", "d"]
+        );
+        assert_eq!(
+            argv(r#"foo bar\\ baz"#, None, &[]).unwrap(),
+            ["foo", "bar baz"]
+        );
+    }
+
+    #[test]
+    fn keeps_quoted_arguments_together() {
+        assert_eq!(
+            argv(r#""/opt/My App/app" "two words" "" x"#, None, &[]).unwrap(),
+            ["/opt/My App/app", "two words", "", "x"]
+        );
+        assert_eq!(argv(r#"foo "unterminated"#, None, &[]), None);
+        assert_eq!(argv(r#""" foo"#, None, &[]), None);
+    }
+
+    #[test]
+    fn expands_simple_field_codes() {
+        assert_eq!(
+            argv(r#"printf 100%% "50%%" %d"#, None, &[]).unwrap(),
+            ["printf", "100%", "50%"]
+        );
+        assert_eq!(
+            argv("foo --name %c --desktop=%k", None, &[]).unwrap(),
+            [
+                "foo",
+                "--name",
+                "Foo Viewer",
+                "--desktop=/usr/share/applications/foo.desktop"
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_icon_to_an_option() {
+        assert_eq!(
+            argv("foo %i", Some("foo-icon"), &[]).unwrap(),
+            ["foo", "--icon", "foo-icon"]
+        );
+        assert_eq!(argv("foo %i", None, &[]).unwrap(), ["foo"]);
+        assert_eq!(
+            argv("foo --theme=%i", Some("foo-icon"), &[]).unwrap(),
+            ["foo", "--theme=foo-icon"]
+        );
+    }
+
+    #[test]
+    fn fills_in_single_targets() {
+        assert_eq!(
+            argv("foo --open=%f", None, &["file:///tmp/a b"]).unwrap(),
+            ["foo", "--open=/tmp/a b"]
+        );
+        assert_eq!(
+            argv("foo --open=%f", None, &[]).unwrap(),
+            ["foo", "--open="]
+        );
+        assert_eq!(argv("foo %f", None, &[]).unwrap(), ["foo"]);
+        assert_eq!(
+            argv("foo %u", None, &["/tmp/a", "/tmp/b"]).unwrap(),
+            ["foo", "file:///tmp/a"]
+        );
+        assert_eq!(
+            argv("foo %u", None, &["https://example.org"]).unwrap(),
+            ["foo", "https://example.org"]
+        );
+    }
+
+    #[test]
+    fn expands_target_lists() {
+        assert_eq!(
+            argv("foo %F --", None, &["/tmp/a", "file:///tmp/b"]).unwrap(),
+            ["foo", "/tmp/a", "/tmp/b", "--"]
+        );
+        assert_eq!(
+            argv("foo %U", None, &["/tmp/a", "https://example.org"]).unwrap(),
+            ["foo", "file:///tmp/a", "https://example.org"]
+        );
+        assert_eq!(argv("foo %U", None, &[]).unwrap(), ["foo"]);
+    }
+}
@@ -0,0 +1,344 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser::SerializeMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum Action {
+    Select,
+    /// launches without closing spellbook
+    SelectKeepAlive,
+    Blacklist,
+    Next,
+    Previous,
+    PageDown,
+    PageUp,
+    First,
+    Last,
+    Exit,
+    DeleteChar,
+    DeleteCharForward,
+    CursorLeft,
+    CursorRight,
+    CursorHome,
+    CursorEnd,
+    CursorWordLeft,
+    CursorWordRight,
+    DeleteWord,
+    KillToStart,
+    KillToEnd,
+    /// inserts the text removed by the last delete_word or kill
+    Yank,
+    /// removes a default binding
+    Unbind,
+}
+
+/// A key with its modifiers, written like `ctrl+alt+n` in the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    const MODIFIERS: KeyModifiers = KeyModifiers::CONTROL
+        .union(KeyModifiers::ALT)
+        .union(KeyModifiers::SHIFT)
+        .union(KeyModifiers::SUPER);
+
+    /// Normalizes shifted keys, as terminals report `shift+a` as `A` and `shift+tab` as `backtab`.
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers & Self::MODIFIERS;
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                let mut upper = c.to_uppercase();
+                match (upper.next(), upper.next()) {
+                    (Some(upper), None) => KeyCode::Char(upper),
+                    _ => KeyCode::Char(c),
+                }
+            }
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key_event: KeyEvent) -> Self {
+        Self::new(key_event.code, key_event.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(chord: &str) -> Result<Self, Self::Err> {
+        let mut parts = chord.split('+').collect::<Vec<&str>>();
+        // a trailing empty part means the key itself is `+`, as in `ctrl++`
+        if chord.ends_with("++") || chord == "+" {
+            parts.pop();
+            parts.pop();
+            parts.push("+");
+        }
+        let Some((key, modifiers)) = parts.split_last() else {
+            return Err(String::from("empty key chord"));
+        };
+        let mut modifier_set = KeyModifiers::NONE;
+        for modifier in modifiers {
+            modifier_set |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                "super" => KeyModifiers::SUPER,
+                "" => return Err(format!("empty modifier in key chord `{}`", chord)),
+                _ => {
+                    return Err(format!(
+                        "unknown modifier `{}` in key chord `{}`, expected ctrl, alt, shift or super",
+                        modifier, chord
+                    ));
+                }
+            };
+        }
+        let code = parse_key(key)
+            .ok_or_else(|| format!("unknown key `{}` in key chord `{}`", key, chord))?;
+        Ok(Self::new(code, modifier_set))
+    }
+}
+
+fn parse_key(key: &str) -> Option<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let code = match key.to_lowercase().as_str() {
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        function => KeyCode::F(function.strip_prefix('f')?.parse().ok()?),
+    };
+    Some(code)
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl"),
+            (KeyModifiers::ALT, "alt"),
+            (KeyModifiers::SHIFT, "shift"),
+            (KeyModifiers::SUPER, "super"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        let key = match self.code {
+            KeyCode::Char(' ') => "space",
+            KeyCode::Char(c) => return write!(f, "{}", c),
+            KeyCode::F(n) => return write!(f, "f{}", n),
+            KeyCode::Enter => "enter",
+            KeyCode::Esc => "esc",
+            KeyCode::Tab => "tab",
+            KeyCode::BackTab => "backtab",
+            KeyCode::Backspace => "backspace",
+            KeyCode::Delete => "delete",
+            KeyCode::Insert => "insert",
+            KeyCode::Left => "left",
+            KeyCode::Right => "right",
+            KeyCode::Up => "up",
+            KeyCode::Down => "down",
+            KeyCode::Home => "home",
+            KeyCode::End => "end",
+            KeyCode::PageUp => "pageup",
+            KeyCode::PageDown => "pagedown",
+            // never parsed from the config
+            _ => "unknown",
+        };
+        write!(f, "{}", key)
+    }
+}
+
+/// The `[keybindings]` table, mapping key chords to actions on top of the defaults.
+#[derive(Debug)]
+pub struct Keybindings(HashMap<KeyChord, Action>);
+
+impl Keybindings {
+    pub fn action(&self, key_event: KeyEvent) -> Option<Action> {
+        self.0.get(&KeyChord::from(key_event)).copied()
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        let bindings = [
+            ("enter", Action::Select),
+            ("alt+enter", Action::SelectKeepAlive),
+            ("alt+delete", Action::Blacklist),
+            ("down", Action::Next),
+            ("tab", Action::Next),
+            ("up", Action::Previous),
+            ("shift+tab", Action::Previous),
+            ("pagedown", Action::PageDown),
+            ("pageup", Action::PageUp),
+            ("esc", Action::Exit),
+            ("backspace", Action::DeleteChar),
+            ("delete", Action::DeleteCharForward),
+            ("left", Action::CursorLeft),
+            ("right", Action::CursorRight),
+            ("ctrl+a", Action::CursorHome),
+            ("home", Action::CursorHome),
+            ("ctrl+e", Action::CursorEnd),
+            ("end", Action::CursorEnd),
+            ("alt+b", Action::CursorWordLeft),
+            ("ctrl+left", Action::CursorWordLeft),
+            ("alt+f", Action::CursorWordRight),
+            ("ctrl+right", Action::CursorWordRight),
+            ("ctrl+w", Action::DeleteWord),
+            ("alt+backspace", Action::DeleteWord),
+            ("ctrl+u", Action::KillToStart),
+            ("ctrl+k", Action::KillToEnd),
+            ("ctrl+y", Action::Yank),
+        ];
+        Self(
+            bindings
+                .into_iter()
+                .map(|(chord, action)| (chord.parse().unwrap(), action))
+                .collect(),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for Keybindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut keybindings = Self::default();
+        for (chord, action) in HashMap::<String, Action>::deserialize(deserializer)? {
+            let chord = chord.parse::<KeyChord>().map_err(de::Error::custom)?;
+            match action {
+                Action::Unbind => keybindings.0.remove(&chord),
+                action => keybindings.0.insert(chord, action),
+            };
+        }
+        Ok(keybindings)
+    }
+}
+
+impl Serialize for Keybindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (chord, action) in &self.0 {
+            map.serialize_entry(&chord.to_string(), action)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keybindings(toml: &str) -> Result<Keybindings, String> {
+        toml::from_str::<HashMap<String, Keybindings>>(toml)
+            .map(|mut tables| tables.remove("keybindings").unwrap())
+            .map_err(|error| error.message().to_string())
+    }
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_chords_like_terminals_report_them() {
+        let chord = "ctrl+alt+n".parse::<KeyChord>().unwrap();
+        assert_eq!(
+            chord,
+            KeyChord::from(press(
+                KeyCode::Char('n'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            ))
+        );
+        assert_eq!(
+            "shift+tab".parse::<KeyChord>().unwrap(),
+            KeyChord::from(press(KeyCode::BackTab, KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            "shift+a".parse::<KeyChord>().unwrap(),
+            KeyChord::from(press(KeyCode::Char('A'), KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            "ctrl++".parse::<KeyChord>().unwrap(),
+            KeyChord::from(press(KeyCode::Char('+'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(chord.to_string(), "ctrl+alt+n");
+        assert_eq!(
+            "alt+PageDown".parse::<KeyChord>().unwrap().to_string(),
+            "alt+pagedown"
+        );
+    }
+
+    #[test]
+    fn merges_bindings_over_the_defaults() {
+        let keybindings = keybindings(
+            r#"[keybindings]
+            "ctrl+n" = "next"
+            "ctrl+j" = "select"
+            "tab" = "unbind"
+            "#,
+        )
+        .unwrap();
+        let action = |code, modifiers| keybindings.action(press(code, modifiers));
+        assert_eq!(
+            action(KeyCode::Char('n'), KeyModifiers::CONTROL),
+            Some(Action::Next)
+        );
+        assert_eq!(
+            action(KeyCode::Char('j'), KeyModifiers::CONTROL),
+            Some(Action::Select)
+        );
+        assert_eq!(action(KeyCode::Tab, KeyModifiers::NONE), None);
+        assert_eq!(
+            action(KeyCode::Down, KeyModifiers::NONE),
+            Some(Action::Next)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_bindings() {
+        assert_eq!(
+            keybindings("[keybindings]\n\"ctrl+foo\" = \"next\"").err(),
+            Some(String::from("unknown key `foo` in key chord `ctrl+foo`"))
+        );
+        assert_eq!(
+            keybindings("[keybindings]\n\"hyper+n\" = \"next\"").err(),
+            Some(String::from(
+                "unknown modifier `hyper` in key chord `hyper+n`, expected ctrl, alt, shift or super"
+            ))
+        );
+        assert!(
+            keybindings("[keybindings]\n\"ctrl+n\" = \"nxt\"")
+                .unwrap_err()
+                .starts_with("unknown variant `nxt`")
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    ffi::CString,
+    fmt,
+    fs::File,
+    io::{ErrorKind, Read, Write},
+};
+
+use nix::{
+    errno::Errno,
+    fcntl::OFlag,
+    libc,
+    sys::{
+        signal::{SigSet, Signal},
+        wait::WaitStatus,
+    },
+    unistd::{ForkResult, Pid, execvp, fork, pipe2, setsid},
+};
+
+/// Why a command could not be launched.
+#[derive(Debug)]
+pub struct LaunchError {
+    pub command: String,
+    pub errno: Errno,
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to launch '{}': {} ({:?})",
+            self.command,
+            self.errno.desc(),
+            self.errno
+        )
+    }
+}
+
+/// A launch whose outcome arrives with the exit of its intermediate child.
+#[derive(Debug)]
+pub struct Launch {
+    pub pid: Pid,
+    command: String,
+}
+
+impl Launch {
+    /// Interprets the exit status of the intermediate child, which exits with
+    /// `0` once the command was executed or with the errno of the failure.
+    pub fn finish(self, status: WaitStatus) -> Result<(), LaunchError> {
+        let errno = match status {
+            WaitStatus::Exited(_, 0) => return Ok(()),
+            WaitStatus::Exited(_, code) => Errno::from_raw(code),
+            _ => Errno::UnknownErrno,
+        };
+        Err(LaunchError {
+            command: self.command,
+            errno,
+        })
+    }
+}
+
+fn describe(argv: &[CString]) -> String {
+    argv.iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs `argv` detached from spellbook in its own session, without blocking.
+///
+/// The command is double forked so it is reparented to init. The intermediate
+/// child waits on a `CLOEXEC` pipe, which is closed without data when `execvp`
+/// succeeds and receives the errno otherwise, and exits accordingly. Its exit
+/// is picked up by the SIGCHLD reaper and passed to [`Launch::finish`].
+pub fn spawn(argv: &[CString]) -> Result<Launch, LaunchError> {
+    let command = describe(argv);
+    let Some(program) = argv.first() else {
+        let errno = Errno::ENOENT;
+        return Err(LaunchError { command, errno });
+    };
+    match unsafe { fork() } {
+        Ok(ForkResult::Parent { child }) => Ok(Launch {
+            pid: child,
+            command,
+        }),
+        Ok(ForkResult::Child) => {
+            let _ = setsid();
+            let errno = match wait_for_exec(program, argv) {
+                Ok(()) => 0,
+                Err(errno) => errno as i32,
+            };
+            unsafe { libc::_exit(errno) }
+        }
+        Err(errno) => Err(LaunchError { command, errno }),
+    }
+}
+
+/// Forks the grandchild that executes `argv` and waits until it did.
+fn wait_for_exec(program: &CString, argv: &[CString]) -> Result<(), Errno> {
+    let (reader, writer) = pipe2(OFlag::O_CLOEXEC)?;
+    match unsafe { fork() }? {
+        ForkResult::Parent { child: _ } => {
+            drop(writer);
+            let mut reader = File::from(reader);
+            let mut errno = [0u8; size_of::<i32>()];
+            let mut read = 0;
+            while read < errno.len() {
+                match reader.read(&mut errno[read..]) {
+                    Ok(0) => break,
+                    Ok(n) => read += n,
+                    Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                    Err(error) => return Err(Errno::from_raw(error.raw_os_error().unwrap_or(0))),
+                }
+            }
+            match read {
+                0 => Ok(()),
+                _ => Err(Errno::from_raw(i32::from_ne_bytes(errno))),
+            }
+        }
+        ForkResult::Child => {
+            drop(reader);
+            let errno = exec(program, argv);
+            let _ = File::from(writer).write_all(&(errno as i32).to_ne_bytes());
+            unsafe { libc::_exit(127) }
+        }
+    }
+}
+
+/// Replaces the spellbook process with `argv`, only returning if `execvp` failed.
+pub fn replace(argv: &[CString]) -> LaunchError {
+    let command = describe(argv);
+    let errno = match argv.first() {
+        Some(program) => exec(program, argv),
+        None => Errno::ENOENT,
+    };
+    LaunchError { command, errno }
+}
+
+fn exec(program: &CString, argv: &[CString]) -> Errno {
+    // SIGCHLD is blocked for the reaper, which must not leak into the launched program
+    let mut mask = SigSet::empty();
+    mask.add(Signal::SIGCHLD);
+    let _ = mask.thread_unblock();
+    let errno = match execvp(program, argv) {
+        Err(errno) => errno,
+        Ok(infallible) => match infallible {},
+    };
+    let _ = mask.thread_block();
+    errno
+}
@@ -0,0 +1,115 @@
+use std::env;
+
+/// The user's message locale, split into its `lang_COUNTRY.ENCODING@MODIFIER` parts.
+#[derive(Debug, Default)]
+pub struct Locale {
+    lang: Option<String>,
+    country: Option<String>,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    /// Reads the locale from `LC_ALL`, `LC_MESSAGES` or `LANG`, in that order.
+    pub fn from_env() -> Self {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    /// Like [`Locale::from_env`], looking the variables up with `var`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(var)
+            .find(|value| !value.is_empty())
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    fn parse(value: &str) -> Self {
+        let (rest, modifier) = match value.split_once('@') {
+            Some((rest, modifier)) => (rest, Some(modifier.to_string())),
+            None => (value, None),
+        };
+        let rest = rest.split('.').next().unwrap_or(rest);
+        let (lang, country) = match rest.split_once('_') {
+            Some((lang, country)) => (lang, Some(country.to_string())),
+            None => (rest, None),
+        };
+        if lang.is_empty() || lang == "C" || lang == "POSIX" {
+            return Self::default();
+        }
+        Self {
+            lang: Some(lang.to_string()),
+            country,
+            modifier,
+        }
+    }
+
+    /// Locale suffixes to try for a localized key, from most to least specific.
+    pub fn candidates(&self) -> Vec<String> {
+        let Some(lang) = &self.lang else {
+            return Vec::new();
+        };
+        let mut candidates = Vec::new();
+        if let (Some(country), Some(modifier)) = (&self.country, &self.modifier) {
+            candidates.push(format!("{}_{}@{}", lang, country, modifier));
+        }
+        if let Some(country) = &self.country {
+            candidates.push(format!("{}_{}", lang, country));
+        }
+        if let Some(modifier) = &self.modifier {
+            candidates.push(format!("{}@{}", lang, modifier));
+        }
+        candidates.push(lang.clone());
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_locales() {
+        for (value, candidates) in [
+            ("de_DE.UTF-8", &["de_DE", "de"][..]),
+            ("de_DE", &["de_DE", "de"]),
+            ("de", &["de"]),
+            ("sr_RS@latin", &["sr_RS@latin", "sr_RS", "sr@latin", "sr"]),
+            (
+                "sr_RS.UTF-8@latin",
+                &["sr_RS@latin", "sr_RS", "sr@latin", "sr"],
+            ),
+            ("sr@latin", &["sr@latin", "sr"]),
+            ("C", &[]),
+            ("C.UTF-8", &[]),
+            ("POSIX", &[]),
+            ("", &[]),
+        ] {
+            assert_eq!(Locale::parse(value).candidates(), candidates, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn skips_empty_variables() {
+        for (vars, candidates) in [
+            (
+                &[("LC_ALL", "fr_FR.UTF-8"), ("LANG", "de_DE.UTF-8")][..],
+                &["fr_FR", "fr"][..],
+            ),
+            (&[("LC_ALL", ""), ("LANG", "de_DE.UTF-8")], &["de_DE", "de"]),
+            (
+                &[("LC_ALL", ""), ("LC_MESSAGES", "nl_BE"), ("LANG", "de_DE")],
+                &["nl_BE", "nl"],
+            ),
+            (&[("LC_MESSAGES", "")], &[]),
+            (&[], &[]),
+        ] {
+            let locale = Locale::from_vars(|var| {
+                vars.iter()
+                    .find(|(name, _)| *name == var)
+                    .map(|(_, value)| value.to_string())
+            });
+            assert_eq!(locale.candidates(), candidates, "{vars:?}");
+        }
+    }
+}
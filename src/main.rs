@@ -1,16 +1,91 @@
 mod application;
+mod args;
+mod cache;
 mod config;
 mod db;
+mod exec;
 mod icon;
+mod keybindings;
+mod launcher;
+mod locale;
 mod message;
+mod search;
 mod spellbook;
+mod terminal;
+mod theme;
+mod tui;
 mod widgets;
 mod worker;
+mod xdg;
 
-fn main() -> std::io::Result<()> {
+use std::{
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use args::Args;
+
+fn main() -> ExitCode {
     let now = std::time::Instant::now();
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(error) if error.is_empty() => {
+            println!("{}", Args::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("spellbook: {}\n{}", error, Args::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let items = args.dmenu.then(|| read_items(io::stdin()));
     let (sender, receiver) = std::sync::mpsc::channel();
+    worker::ChildReaper::spawn(sender.clone());
+    let (dmenu, rebuild_cache) = (args.dmenu, args.rebuild_cache);
+    // queries the terminal, so it has to come before anything reads terminal input
+    let mut spellbook = spellbook::Spellbook::new(receiver, now, args, items);
     worker::FileWatcher::spawn(sender.clone());
     worker::EventWatcher::spawn(sender.clone());
-    spellbook::Spellbook::new(receiver, now).run()
+    if !dmenu {
+        worker::ApplicationWatcher::spawn(sender.clone());
+        worker::ApplicationLoader::spawn(sender.clone(), rebuild_cache);
+    }
+    spellbook.run().unwrap_or_else(|error| {
+        eprintln!("spellbook: {}", error);
+        ExitCode::FAILURE
+    })
+}
+
+/// Reads newline-separated dmenu items, replacing invalid UTF-8 instead of stopping at it.
+fn read_items(mut input: impl Read) -> Vec<String> {
+    let mut bytes = Vec::new();
+    // keeps whatever was read before an error
+    let _ = input.read_to_end(&mut bytes);
+    if bytes.is_empty() {
+        return Vec::new();
+    }
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(&bytes);
+    bytes
+        .split(|&byte| byte == b'\n')
+        .map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            String::from_utf8_lossy(line).into_owned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_items_past_invalid_utf8() {
+        assert_eq!(
+            read_items(&b"firefox\r\nbad \xff name\n\nkitty\n"[..]),
+            ["firefox", "bad \u{fffd} name", "", "kitty"]
+        );
+        assert_eq!(read_items(&b"no newline"[..]), ["no newline"]);
+        assert_eq!(read_items(&b"\n"[..]), [""]);
+        assert!(read_items(&b""[..]).is_empty());
+    }
 }
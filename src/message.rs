@@ -1,7 +1,17 @@
-use crossterm::event::KeyEvent;
+use std::path::PathBuf;
+
+use crossterm::event::{KeyEvent, MouseEvent};
+use nix::sys::wait::WaitStatus;
+
+use crate::{application::Application, worker::LoadTimings, xdg::DesktopFile};
 
 pub enum Message {
     Input(KeyEvent),
+    Mouse(MouseEvent),
     Redraw,
     ReloadConfig,
+    ChildExited(WaitStatus),
+    ApplicationsChanged(Vec<PathBuf>),
+    ApplicationsLoaded(Vec<Application>),
+    ApplicationsLoadingFinished(Vec<DesktopFile>, LoadTimings),
 }
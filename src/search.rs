@@ -0,0 +1,793 @@
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    application::Application,
+    config::{SearchConfig, SearchMode, SearchWeights},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchField {
+    Name,
+    GenericName,
+    Keywords,
+    Comment,
+    Categories,
+    Exec,
+}
+
+impl SearchField {
+    const ALL: [SearchField; 6] = [
+        SearchField::Name,
+        SearchField::GenericName,
+        SearchField::Keywords,
+        SearchField::Comment,
+        SearchField::Categories,
+        SearchField::Exec,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchField::Name => "name",
+            SearchField::GenericName => "generic name",
+            SearchField::Keywords => "keywords",
+            SearchField::Comment => "comment",
+            SearchField::Categories => "categories",
+            SearchField::Exec => "exec",
+        }
+    }
+
+    fn weight(self, weights: &SearchWeights) -> u32 {
+        match self {
+            SearchField::Name => weights.name,
+            SearchField::GenericName => weights.generic_name,
+            SearchField::Keywords => weights.keywords,
+            SearchField::Comment => weights.comment,
+            SearchField::Categories => weights.categories,
+            SearchField::Exec => weights.exec,
+        }
+    }
+
+    /// The `index`th value of the field, so a match can refer to it without copying it.
+    fn value(self, application: &Application, index: usize) -> Option<&str> {
+        match self {
+            SearchField::Name => match index {
+                0 => Some(&application.name),
+                1 if application.name != application.untranslated_name => {
+                    Some(&application.untranslated_name)
+                }
+                _ => None,
+            },
+            SearchField::GenericName => application.generic_name.as_deref().filter(|_| index == 0),
+            SearchField::Keywords => application.keywords.get(index).map(String::as_str),
+            SearchField::Comment => application.comment.as_deref().filter(|_| index == 0),
+            SearchField::Categories => application.categories.get(index).map(String::as_str),
+            SearchField::Exec => (index == 0).then(|| application.exec.basename()),
+        }
+    }
+}
+
+/// The best scoring field of an application for the current filter.
+#[derive(Clone, Debug)]
+pub struct SearchMatch {
+    pub field: SearchField,
+    /// index of the matched value of `field`
+    index: usize,
+    pub score: u32,
+}
+
+impl SearchMatch {
+    pub fn text<'a>(&self, application: &'a Application) -> &'a str {
+        self.field
+            .value(application, self.index)
+            .unwrap_or_default()
+    }
+}
+
+/// A filter folded once, to be matched against every text.
+pub struct Query {
+    text: FoldedText,
+    normalize: bool,
+}
+
+impl Query {
+    pub fn new(filter: &str, config: &SearchConfig) -> Self {
+        Self {
+            text: FoldedText::new(filter, config.normalize),
+            normalize: config.normalize,
+        }
+    }
+
+    /// Folds `text` into units, unless it cannot match.
+    fn fold(&self, text: &str) -> Option<FoldedText> {
+        self.may_match(text)
+            .then(|| FoldedText::new(text, self.normalize))
+    }
+
+    /// Whether the folded chars of `text` contain the query as a subsequence, which every mode requires.
+    ///
+    /// Checked before folding `text` into units, as most texts do not match.
+    fn may_match(&self, text: &str) -> bool {
+        let query = &self.text.folded;
+        if text.is_ascii() && query.is_ascii() {
+            let mut rest = text.as_bytes();
+            return query.bytes().all(|needle| {
+                // `| 0x20` lowercases ascii letters and keeps them apart from everything else
+                let position = if needle.is_ascii_lowercase() {
+                    rest.iter().position(|&byte| byte | 0x20 == needle)
+                } else {
+                    rest.iter().position(|&byte| byte == needle)
+                };
+                match position {
+                    Some(position) => {
+                        rest = &rest[position + 1..];
+                        true
+                    }
+                    None => false,
+                }
+            });
+        }
+        let mut remaining = query.chars().peekable();
+        text.chars().any(|c| {
+            fold_char(c, self.normalize, |c| {
+                remaining.next_if_eq(&c);
+            });
+            remaining.peek().is_none()
+        })
+    }
+}
+
+/// Whether every text matching `previous` may also match `filter`, so only the previous matches need to be searched.
+///
+/// Matching compares whole graphemes, so `filter` has to keep every grapheme of `previous` as is:
+/// appending a combining mark, a ZWJ sequence or the second regional indicator of a flag changes the last one.
+pub fn extends(previous: &str, filter: &str) -> bool {
+    let mut graphemes = filter.graphemes(true);
+    previous
+        .graphemes(true)
+        .all(|grapheme| graphemes.next() == Some(grapheme))
+}
+
+/// Matches `filter` against every searchable field, weighting each field's score.
+///
+/// Fields with a weight of `0` are not searched.
+pub fn search(
+    application: &Application,
+    query: &Query,
+    config: &SearchConfig,
+) -> Option<SearchMatch> {
+    let mut best: Option<SearchMatch> = None;
+    for field in SearchField::ALL {
+        let weight = field.weight(&config.weights);
+        if weight == 0 {
+            continue;
+        }
+        for (index, text) in (0..)
+            .map_while(|index| field.value(application, index))
+            .enumerate()
+        {
+            if text.is_empty() {
+                continue;
+            }
+            let Some(text_score) = score_text(text, query, config) else {
+                continue;
+            };
+            // saturates, as the weights come straight from the config
+            let score = weight.saturating_mul(text_score);
+            if best.as_ref().is_none_or(|best| score > best.score) {
+                best = Some(SearchMatch {
+                    field,
+                    index,
+                    score,
+                });
+            }
+        }
+    }
+    best
+}
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = 3;
+const SCORE_GAP_EXTENSION: i32 = 1;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CAMEL_CASE: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+/// Matches `query` against `text` case-insensitively, ignoring diacritics if the query was normalized.
+///
+/// Both are compared grapheme by grapheme, and the positions of the best match are grapheme indices into `text`.
+pub fn match_positions(text: &str, query: &Query, config: &SearchConfig) -> Option<Vec<usize>> {
+    let query_len = query.text.units.len();
+    if query_len == 0 {
+        return Some(Vec::new());
+    }
+    let text = query.fold(text)?;
+    let query = &query.text;
+    let positions = match config.mode {
+        SearchMode::Fuzzy => fuzzy_positions(&text, query)?,
+        mode => {
+            let start = substring_start(&text, query, mode)?;
+            (start..start + query_len).collect()
+        }
+    };
+    let mut positions = positions
+        .into_iter()
+        .map(|unit| text.units[unit].grapheme)
+        .collect::<Vec<usize>>();
+    positions.dedup();
+    Some(positions)
+}
+
+/// Scores the match of [`match_positions`], without the positions that are only needed for highlighting.
+pub fn score_text(text: &str, query: &Query, config: &SearchConfig) -> Option<u32> {
+    let query_len = query.text.units.len();
+    if query_len == 0 {
+        return Some(0);
+    }
+    let text = query.fold(text)?;
+    let query = &query.text;
+    let score = match config.mode {
+        SearchMode::Fuzzy => fuzzy_score(&text, query)?,
+        mode => {
+            let start = substring_start(&text, query, mode)?;
+            text.score(start..start + query_len)
+        }
+    };
+    Some(score.max(1) as u32)
+}
+
+/// Start of the best scoring occurrence of `query` in `text`, which has to be all of it in exact mode.
+fn substring_start(text: &FoldedText, query: &FoldedText, mode: SearchMode) -> Option<usize> {
+    let query_len = query.units.len();
+    let matches_at = |start: usize| (0..query_len).all(|i| text.same_unit(start + i, query, i));
+    match mode {
+        SearchMode::Exact => (text.units.len() == query_len && matches_at(0)).then_some(0),
+        _ => (0..=text.units.len().checked_sub(query_len)?)
+            .filter(|&start| matches_at(start))
+            .max_by_key(|&start| {
+                (
+                    text.score(start..start + query_len),
+                    std::cmp::Reverse(start),
+                )
+            }),
+    }
+}
+
+/// A text split into lowercased units that are compared one by one.
+///
+/// Each unit is a whole grapheme cluster, so a match never splits a user-perceived character.
+/// When normalizing, graphemes are decomposed, stripped of combining marks and transliterated,
+/// and every remaining char becomes a unit of its own, so `ß` matches `ss` and `カナ` matches `kana`.
+struct FoldedText {
+    folded: String,
+    units: Vec<Unit>,
+}
+
+struct Unit {
+    /// end of the unit in `folded`
+    end: usize,
+    /// the unit if it is a single char, compared instead of slicing `folded`
+    key: Option<char>,
+    /// index of the original grapheme
+    grapheme: usize,
+    /// bonus for matching the unit, see [`bonus`]
+    bonus: i32,
+}
+
+impl FoldedText {
+    fn new(text: &str, normalize: bool) -> Self {
+        let mut folded = String::with_capacity(text.len());
+        let mut units = Vec::with_capacity(text.len());
+        let mut previous = None;
+        // every ascii char is a grapheme of its own, except for "\r\n"
+        if text.is_ascii() && !text.contains('\r') {
+            folded.push_str(text);
+            folded.make_ascii_lowercase();
+            units.extend(text.chars().enumerate().map(|(i, c)| Unit {
+                end: i + 1,
+                key: Some(c.to_ascii_lowercase()),
+                grapheme: i,
+                bonus: bonus(previous.replace(c), c),
+            }));
+            return Self { folded, units };
+        }
+        let mut i = 0;
+        for_each_grapheme(text, |grapheme| {
+            let base = grapheme.chars().next().unwrap_or(' ');
+            let start = folded.len();
+            grapheme
+                .chars()
+                .for_each(|c| fold_char(c, normalize, |c| folded.push(c)));
+            if normalize {
+                for (offset, c) in folded[start..].char_indices() {
+                    units.push(Unit {
+                        end: start + offset + c.len_utf8(),
+                        key: Some(c),
+                        grapheme: i,
+                        bonus: bonus(previous.replace(base), base),
+                    });
+                }
+            } else {
+                let mut chars = folded[start..].chars();
+                units.push(Unit {
+                    end: folded.len(),
+                    key: chars.next().filter(|_| chars.next().is_none()),
+                    grapheme: i,
+                    bonus: bonus(previous.replace(base), base),
+                });
+            }
+            i += 1;
+        });
+        Self { folded, units }
+    }
+
+    fn unit(&self, i: usize) -> &str {
+        let start = i
+            .checked_sub(1)
+            .map_or(0, |previous| self.units[previous].end);
+        &self.folded[start..self.units[i].end]
+    }
+
+    /// Whether unit `i` equals unit `j` of `other`.
+    fn same_unit(&self, i: usize, other: &FoldedText, j: usize) -> bool {
+        match (self.units[i].key, other.units[j].key) {
+            (None, None) => self.unit(i) == other.unit(j),
+            (key, other_key) => key == other_key,
+        }
+    }
+
+    /// Scores matching the units at `positions`, rewarding word starts and consecutive units and penalizing gaps.
+    fn score(&self, positions: impl IntoIterator<Item = usize>) -> i32 {
+        let mut score = 0;
+        let mut previous: Option<usize> = None;
+        for position in positions {
+            let bonus = self.units[position].bonus;
+            score += SCORE_MATCH;
+            score += match previous {
+                None => bonus * BONUS_FIRST_CHAR_MULTIPLIER,
+                Some(previous) if position == previous + 1 => bonus + BONUS_CONSECUTIVE,
+                Some(previous) => bonus - gap_penalty(position - previous - 1),
+            };
+            previous = Some(position);
+        }
+        score
+    }
+}
+
+/// Calls `f` with every grapheme cluster of `text`, only segmenting the parts around non-ascii chars.
+///
+/// Two adjacent ascii chars other than "\r\n" are always separate graphemes,
+/// and the rules looking further back, for emoji ZWJ sequences and flags, never involve ascii chars.
+fn for_each_grapheme<'a>(text: &'a str, mut f: impl FnMut(&'a str)) {
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    for end in 1..=bytes.len() {
+        let boundary = end == bytes.len()
+            || bytes[end - 1].is_ascii()
+                && bytes[end].is_ascii()
+                && (bytes[end - 1], bytes[end]) != (b'\r', b'\n');
+        if !boundary {
+            continue;
+        }
+        let piece = &text[start..end];
+        if piece.len() == 1 {
+            f(piece);
+        } else {
+            piece.graphemes(true).for_each(&mut f);
+        }
+        start = end;
+    }
+}
+
+/// Lowercases `c`, and when normalizing romanizes kana or decomposes it, dropping combining marks and transliterating the rest.
+fn fold_char(c: char, normalize: bool, mut push: impl FnMut(char)) {
+    if c.is_ascii() {
+        push(c.to_ascii_lowercase());
+        return;
+    }
+    if !normalize {
+        c.to_lowercase().for_each(push);
+        return;
+    }
+    match romanize_kana(c) {
+        Some(romaji) => romaji.chars().for_each(push),
+        None => decompose_canonical(c, |c| {
+            if is_combining_mark(c) {
+                return;
+            }
+            match transliterate(c) {
+                Some(latin) => latin.chars().for_each(&mut push),
+                None => c.to_lowercase().for_each(&mut push),
+            }
+        }),
+    }
+}
+
+/// Latin letters that do not decompose into a base letter and a combining mark,
+/// and the Hangul jamo that syllables decompose into, in Revised Romanization.
+fn transliterate(c: char) -> Option<&'static str> {
+    let latin = match c {
+        'ß' | 'ẞ' => "ss",
+        'æ' | 'Æ' => "ae",
+        'œ' | 'Œ' => "oe",
+        'ø' | 'Ø' => "o",
+        'ł' | 'Ł' => "l",
+        'đ' | 'Đ' | 'ð' | 'Ð' => "d",
+        'þ' | 'Þ' => "th",
+        'ı' => "i",
+        'ħ' | 'Ħ' => "h",
+        'ŧ' | 'Ŧ' => "t",
+        'ŋ' | 'Ŋ' => "ng",
+        'ĸ' => "q",
+        'ſ' => "s",
+        '\u{1100}'..='\u{1112}' => HANGUL_INITIALS[c as usize - 0x1100],
+        '\u{1161}'..='\u{1175}' => HANGUL_VOWELS[c as usize - 0x1161],
+        '\u{11A8}'..='\u{11C2}' => HANGUL_FINALS[c as usize - 0x11A8],
+        _ => return None,
+    };
+    Some(latin)
+}
+
+#[rustfmt::skip]
+const HANGUL_INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p", "h",
+];
+#[rustfmt::skip]
+const HANGUL_VOWELS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+#[rustfmt::skip]
+const HANGUL_FINALS: [&str; 27] = [
+    "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p", "t",
+    "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+
+/// Hepburn romanization of a single hiragana or katakana, without combining it with its neighbours.
+///
+/// Looked up before decomposing, which would strip the voicing marks of e.g. `が`.
+fn romanize_kana(c: char) -> Option<&'static str> {
+    let hiragana = match c {
+        '\u{3041}'..='\u{3096}' => c as usize,
+        '\u{30A1}'..='\u{30F6}' => c as usize - 0x60,
+        // the long vowel mark only lengthens the previous vowel
+        'ー' => return Some(""),
+        _ => return None,
+    };
+    Some(KANA[hiragana - 0x3041])
+}
+
+/// From `ぁ` (U+3041) to `ゖ` (U+3096), katakana being offset by 0x60.
+#[rustfmt::skip]
+const KANA: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o",
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go",
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo",
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do",
+    "na", "ni", "nu", "ne", "no",
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po",
+    "ma", "mi", "mu", "me", "mo",
+    "ya", "ya", "yu", "yu", "yo", "yo",
+    "ra", "ri", "ru", "re", "ro",
+    "wa", "wa", "i", "e", "o", "n",
+    "vu", "ka", "ke",
+];
+
+/// Bonus for matching a unit, given the first char of its grapheme and of the previous unit's,
+/// rewarding word starts and camelCase or digit boundaries.
+fn bonus(previous: Option<char>, c: char) -> i32 {
+    match previous {
+        _ if !c.is_alphanumeric() => 0,
+        None => BONUS_BOUNDARY,
+        Some(p) if !p.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(p) if p.is_lowercase() && c.is_uppercase() => BONUS_CAMEL_CASE,
+        Some(p) if !p.is_numeric() && c.is_numeric() => BONUS_CAMEL_CASE,
+        Some(_) => 0,
+    }
+}
+
+fn gap_penalty(gap: usize) -> i32 {
+    SCORE_GAP_START + (gap as i32 - 1) * SCORE_GAP_EXTENSION
+}
+
+const NONE: i32 = i32::MIN / 2;
+
+/// Finds the highest scoring subsequence match, scored like [`FoldedText::score`].
+fn fuzzy_positions(text: &FoldedText, query: &FoldedText) -> Option<Vec<usize>> {
+    if !is_subsequence(text, query) {
+        return None;
+    }
+    let (n, m) = (text.units.len(), query.units.len());
+    // row-major `m x n` matrices, row `i` holding the best scores with query unit `i` at text unit `j`
+    let mut scores = vec![NONE; m * n];
+    let mut predecessors = vec![0usize; m * n];
+    for i in 0..m {
+        let (previous, row) = scores.split_at_mut(i * n);
+        fuzzy_row(
+            text,
+            query,
+            i,
+            &previous[previous.len().saturating_sub(n)..],
+            &mut row[..n],
+            Some(&mut predecessors[i * n..(i + 1) * n]),
+        );
+    }
+
+    let last_row = (m - 1) * n;
+    let mut j = (0..n).max_by_key(|&j| (scores[last_row + j], std::cmp::Reverse(j)))?;
+    if scores[last_row + j] <= NONE / 2 {
+        return None;
+    }
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = predecessors[i * n + j];
+    }
+    Some(positions)
+}
+
+/// The score of [`fuzzy_positions`], only keeping two rows of scores.
+fn fuzzy_score(text: &FoldedText, query: &FoldedText) -> Option<i32> {
+    if !is_subsequence(text, query) {
+        return None;
+    }
+    let n = text.units.len();
+    let mut previous = vec![NONE; n];
+    let mut row = vec![NONE; n];
+    for i in 0..query.units.len() {
+        fuzzy_row(text, query, i, &previous, &mut row, None);
+        std::mem::swap(&mut previous, &mut row);
+    }
+    previous.into_iter().max().filter(|&score| score > NONE / 2)
+}
+
+fn is_subsequence(text: &FoldedText, query: &FoldedText) -> bool {
+    let mut remaining = 0;
+    for j in 0..text.units.len() {
+        if remaining < query.units.len() && text.same_unit(j, query, remaining) {
+            remaining += 1;
+        }
+    }
+    remaining == query.units.len()
+}
+
+/// Fills `row` with the best scores having query unit `i` at each text unit, given the `previous` row.
+///
+/// `predecessors` receives the text unit of query unit `i - 1` each score builds on.
+fn fuzzy_row(
+    text: &FoldedText,
+    query: &FoldedText,
+    i: usize,
+    previous: &[i32],
+    row: &mut [i32],
+    mut predecessors: Option<&mut [usize]>,
+) {
+    row.fill(NONE);
+    if i == 0 {
+        for (j, score) in row.iter_mut().enumerate() {
+            if text.same_unit(j, query, 0) {
+                *score = SCORE_MATCH + text.units[j].bonus * BONUS_FIRST_CHAR_MULTIPLIER;
+            }
+        }
+        return;
+    }
+    // best score of the previous row at any k <= j - 2, including the gap penalty up to j
+    let mut gap_best = NONE;
+    let mut gap_best_index = 0;
+    for j in i..row.len() {
+        if j >= 2 {
+            let candidate = previous[j - 2] - SCORE_GAP_START;
+            gap_best -= SCORE_GAP_EXTENSION;
+            if candidate >= gap_best {
+                gap_best = candidate;
+                gap_best_index = j - 2;
+            }
+        }
+        if !text.same_unit(j, query, i) {
+            continue;
+        }
+        let consecutive = previous[j - 1] + BONUS_CONSECUTIVE;
+        let (best, predecessor) = if consecutive >= gap_best {
+            (consecutive, j - 1)
+        } else {
+            (gap_best, gap_best_index)
+        };
+        if best > NONE / 2 {
+            row[j] = best + SCORE_MATCH + text.units[j].bonus;
+            if let Some(predecessors) = predecessors.as_deref_mut() {
+                predecessors[j] = predecessor;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(text: &str, filter: &str, mode: SearchMode) -> Option<Vec<usize>> {
+        let config = SearchConfig {
+            mode,
+            ..SearchConfig::default()
+        };
+        match_positions(text, &Query::new(filter, &config), &config)
+    }
+
+    fn normalized(text: &str, filter: &str, mode: SearchMode) -> Option<Vec<usize>> {
+        let config = SearchConfig {
+            mode,
+            normalize: true,
+            ..SearchConfig::default()
+        };
+        match_positions(text, &Query::new(filter, &config), &config)
+    }
+
+    #[test]
+    fn matches_cjk_by_grapheme() {
+        assert_eq!(
+            positions("日本語入力", "本語", SearchMode::Substring),
+            Some(vec![1, 2])
+        );
+        assert_eq!(
+            positions("日本語入力", "日入", SearchMode::Fuzzy),
+            Some(vec![0, 3])
+        );
+    }
+
+    #[test]
+    fn counts_emoji_sequences_as_one_grapheme() {
+        assert_eq!(
+            positions("👩‍💻 Dev Tools", "dev", SearchMode::Fuzzy),
+            Some(vec![2, 3, 4])
+        );
+        assert_eq!(
+            positions("🇩🇪 Wetter", "wet", SearchMode::Substring),
+            Some(vec![2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn keeps_combining_characters_with_their_base() {
+        let text = "Cafe\u{301} Noir";
+        assert_eq!(
+            positions(text, "cafe\u{301}", SearchMode::Substring),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(positions(text, "cafe", SearchMode::Substring), None);
+        assert_eq!(
+            positions(text, "noir", SearchMode::Fuzzy),
+            Some(vec![5, 6, 7, 8])
+        );
+    }
+
+    #[test]
+    fn folds_case_when_lowercase_changes_length() {
+        assert_eq!(
+            positions("STRAẞE", "straße", SearchMode::Exact),
+            Some(vec![0, 1, 2, 3, 4, 5])
+        );
+        assert_eq!(
+            positions("İstanbul", "stan", SearchMode::Substring),
+            Some(vec![1, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn ignores_diacritics_when_normalizing() {
+        assert_eq!(
+            normalized("Écran", "ecran", SearchMode::Exact),
+            Some(vec![0, 1, 2, 3, 4])
+        );
+        assert_eq!(
+            normalized("Cafe\u{301} Noir", "cafe", SearchMode::Substring),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(
+            normalized("Öffnungszeiten", "offnung", SearchMode::Substring),
+            Some(vec![0, 1, 2, 3, 4, 5, 6])
+        );
+        assert_eq!(
+            normalized("ecran", "écran", SearchMode::Exact),
+            Some(vec![0, 1, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn maps_transliterations_back_to_the_original_grapheme() {
+        assert_eq!(
+            normalized("Straße", "strasse", SearchMode::Exact),
+            Some(vec![0, 1, 2, 3, 4, 5])
+        );
+        assert_eq!(
+            normalized("Łódź Maps", "lodz", SearchMode::Fuzzy),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(
+            normalized("Ærø", "aero", SearchMode::Substring),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(
+            normalized("日本語", "本", SearchMode::Substring),
+            Some(vec![1])
+        );
+    }
+
+    #[test]
+    fn finds_kana_and_hangul_names_by_their_romanization() {
+        assert_eq!(
+            normalized("カナ入力", "kana", SearchMode::Substring),
+            Some(vec![0, 1])
+        );
+        assert_eq!(
+            normalized("ターミナル", "taminaru", SearchMode::Exact),
+            Some(vec![0, 2, 3, 4])
+        );
+        assert_eq!(
+            normalized("ひらがな", "hiragana", SearchMode::Exact),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(
+            normalized("한글 입력기", "hangeul", SearchMode::Substring),
+            Some(vec![0, 1])
+        );
+        assert_eq!(
+            normalized("서울 지도", "seoul", SearchMode::Fuzzy),
+            Some(vec![0, 1])
+        );
+        assert_eq!(positions("カナ入力", "kana", SearchMode::Substring), None);
+        // Han characters are not romanized
+        assert_eq!(normalized("日本語", "nihon", SearchMode::Fuzzy), None);
+    }
+
+    #[test]
+    fn saturates_huge_weights() {
+        let application = Application::from_item(0, String::from("Firefox"));
+        let config = SearchConfig {
+            weights: SearchWeights {
+                name: u32::MAX,
+                ..SearchWeights::default()
+            },
+            ..SearchConfig::default()
+        };
+        assert_eq!(
+            search(&application, &Query::new("fire", &config), &config)
+                .map(|search_match| search_match.score),
+            Some(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn segments_like_the_full_grapheme_rules() {
+        for text in [
+            "Cafe\u{301} Noir",
+            "ab\r\ncd\r",
+            "\tx\u{301}\u{302}",
+            "👩‍💻 Dev",
+            "x🇩🇪🇫🇷y",
+            "a\u{200d}b",
+            "日本語 input",
+        ] {
+            let mut graphemes = Vec::new();
+            for_each_grapheme(text, |grapheme| graphemes.push(grapheme));
+            assert_eq!(graphemes, text.graphemes(true).collect::<Vec<&str>>());
+        }
+    }
+
+    #[test]
+    fn scores_fuzzy_matches_like_their_positions() {
+        let config = SearchConfig::default();
+        for (text, filter) in [
+            ("Firefox Web Browser", "fwb"),
+            ("GNU Image Manipulation Program", "gimp"),
+            ("libreoffice-calc", "calc"),
+            ("aaa bab", "ab"),
+            ("Cafe\u{301} Noir", "cn"),
+        ] {
+            let query = Query::new(filter, &config);
+            let text = FoldedText::new(text, false);
+            let positions = fuzzy_positions(&text, &query.text).unwrap();
+            assert_eq!(fuzzy_score(&text, &query.text), Some(text.score(positions)));
+        }
+    }
+}
@@ -1,128 +1,322 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use nix::{
-    sys::wait::{WaitPidFlag, WaitStatus, waitpid},
-    unistd::{ForkResult, execvp, fork, setsid},
-};
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use nix::{sys::wait::WaitStatus, unistd::Pid};
 use ratatui::{
     Frame,
     buffer::Buffer,
     layout::{Constraint, Layout, Margin, Position, Rect},
-    symbols,
     widgets::{Block, Borders, StatefulWidget, Widget},
 };
 use std::{
+    collections::HashMap,
+    ffi::CString,
     io::{self},
-    process::exit,
+    process::ExitCode,
     sync::mpsc,
-    thread,
     time::{Duration, Instant},
 };
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    config::Config,
+    application::Application,
+    args::Args,
+    config::{Config, TerminalMode},
+    keybindings::Action,
+    launcher::{self, Launch},
     message::Message,
+    search::SearchField,
+    terminal,
+    theme::{self, Palette, Theme},
+    tui,
     widgets::{
         application_list::{ApplicationList, ApplicationListState},
         counter::Counter,
         info::{Info, InfoState},
         input::{Input, InputState},
     },
+    worker::LoadTimings,
 };
 
 #[derive(Debug)]
 pub struct Spellbook {
     mode: RunMode,
     receiver: mpsc::Receiver<Message>,
+    args: Args,
     config: Config,
+    theme: Theme,
+    /// the terminal's background color, if queried at startup
+    background: Option<(f32, f32, f32)>,
     state: SpellbookState,
     startup_instant: Instant,
+    /// items picked in dmenu mode, printed to stdout on exit
+    selections: Vec<String>,
+    clear_terminal: bool,
+    launches: HashMap<Pid, PendingLaunch>,
+    /// time and row of the last click on the list, to detect double-clicks
+    last_click: Option<(Instant, usize)>,
+}
+
+/// A spawned application waiting for the outcome of its exec.
+#[derive(Debug)]
+struct PendingLaunch {
+    launch: Launch,
+    application: Application,
+    keep_alive: bool,
 }
 
 #[derive(Debug, Default)]
 enum RunMode {
     #[default]
     Running,
-    Exit,
+    Exit(ExitCode),
 }
 
 impl Spellbook {
-    pub fn new(receiver: mpsc::Receiver<Message>, startup_instant: Instant) -> Self {
+    const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+    const SCROLL_ROWS: isize = 3;
+
+    /// Launches applications, or picks from `items` in dmenu mode.
+    pub fn new(
+        receiver: mpsc::Receiver<Message>,
+        startup_instant: Instant,
+        args: Args,
+        items: Option<Vec<String>>,
+    ) -> Self {
         let mode = RunMode::Running;
-        let config = Config::load();
-        let state = SpellbookState::default();
+        let config = Self::load_config(&args);
+        let no_color = theme::no_color();
+        let background = theme::needs_background(&config.theme, no_color)
+            .then(tui::query_background)
+            .flatten();
+        let palette = Palette::new(config.theme.mode, background, no_color);
+        let theme = Theme::new(&config.theme, palette);
+        let application_list = match items {
+            Some(items) => ApplicationListState::from_items(items),
+            None => ApplicationListState::new(),
+        };
+        let state = SpellbookState::new(application_list);
         Self {
             mode,
             receiver,
+            args,
             config,
+            theme,
+            background,
             state,
             startup_instant,
+            selections: Vec::new(),
+            clear_terminal: false,
+            launches: HashMap::new(),
+            last_click: None,
         }
     }
 
+    fn load_config(args: &Args) -> Config {
+        let mut config = Config::load();
+        Self::apply_args(&mut config, args);
+        config
+    }
+
+    fn apply_args(config: &mut Config, args: &Args) {
+        if let Some(prompt) = &args.prompt {
+            config.input.placeholder = prompt.clone();
+        }
+    }
+
+    /// Reloads the config, keeping the current one if the new one is invalid.
     fn reload_config(&mut self) {
-        self.config = Config::load();
+        match Config::try_load() {
+            Ok(mut config) => {
+                Self::apply_args(&mut config, &self.args);
+                let palette = Palette::new(config.theme.mode, self.background, theme::no_color());
+                self.theme = Theme::new(&config.theme, palette);
+                self.config = config;
+            }
+            Err(error) => {
+                let message = format!("invalid config: {}", error.message().trim());
+                self.state.info.update_message(Some(message));
+                return;
+            }
+        }
+        let _ = tui::set_mouse_capture(self.config.mouse.enable);
+        if !self.args.dmenu {
+            self.state.application_list.sort(&self.config);
+        }
+        self.update_filter();
+    }
+
+    pub fn run(&mut self) -> io::Result<ExitCode> {
+        let mut terminal = tui::init(self.args.dmenu)?;
+        tui::set_mouse_capture(self.config.mouse.enable)?;
+        if !self.state.application_list.loading {
+            self.show_startup_duration(None);
+        }
+        let exit_code = loop {
+            match self.mode {
+                RunMode::Running => {
+                    if std::mem::take(&mut self.clear_terminal) {
+                        terminal.clear()?;
+                    }
+                    terminal.draw(|frame| self.draw(frame))?;
+                    self.handle_messages();
+                }
+                RunMode::Exit(exit_code) => break exit_code,
+            }
+        };
+        tui::restore();
+        for selection in &self.selections {
+            println!("{}", selection);
+        }
+        Ok(exit_code)
     }
 
-    pub fn run(&mut self) -> io::Result<()> {
-        let mut terminal = ratatui::init();
-        self.state.info.update_message(Some(format!(
+    fn show_startup_duration(&mut self, timings: Option<LoadTimings>) {
+        let mut message = format!(
             "startup duration: {}ms",
             self.startup_instant.elapsed().as_millis()
-        )));
-        while let RunMode::Running = &self.mode {
-            terminal.draw(|frame| self.draw(frame))?;
-            self.handle_messages();
+        );
+        if let Some(timings) = timings {
+            message += &format!(
+                " (scan {}ms, parse {}ms, db merge {}ms, sort {}ms)",
+                timings.scan.as_millis(),
+                timings.parse.as_millis(),
+                timings.db_merge.as_millis(),
+                timings.sort.as_millis()
+            );
         }
-        ratatui::restore();
-        Ok(())
+        self.state.info.update_message(Some(message));
     }
 
     fn draw(&mut self, frame: &mut Frame) {
+        // rendering scrolls the input, so the cursor is placed afterwards
+        frame.render_widget(&mut *self, frame.area());
         frame
             .set_cursor_position(self.cursor_position(self.state.input.relative_cursor_position()));
-        frame.render_widget(self, frame.area());
     }
 
     fn handle_messages(&mut self) {
         let message = self.receiver.recv().unwrap();
         match message {
             Message::Input(key_event) => self.handle_input(key_event),
+            Message::Mouse(mouse_event) => self.handle_mouse(mouse_event),
             Message::Redraw => {}
             Message::ReloadConfig => self.reload_config(),
+            Message::ChildExited(status) => self.finish_launch(status),
+            Message::ApplicationsChanged(paths) => {
+                self.state
+                    .application_list
+                    .refresh(&paths, &self.state.input.filter, &self.config)
+            }
+            Message::ApplicationsLoaded(applications) => self.state.application_list.extend(
+                applications,
+                &self.state.input.filter,
+                &self.config,
+            ),
+            Message::ApplicationsLoadingFinished(desktop_files, mut timings) => {
+                timings.sort = self.state.application_list.loading_sort_duration;
+                self.state.application_list.finish_loading(
+                    desktop_files,
+                    &self.state.input.filter,
+                    &self.config,
+                );
+                self.show_startup_duration(Some(timings));
+            }
         }
     }
 
     fn handle_input(&mut self, key_event: KeyEvent) {
-        match (key_event.modifiers, key_event.code) {
-            (KeyModifiers::ALT, KeyCode::Enter) => self.select_application(true),
-            (KeyModifiers::ALT, KeyCode::Delete) => self.blacklist_application(),
-            (_, KeyCode::Char(to_insert)) => {
-                self.state.input.enter_char(to_insert);
-                self.state.application_list.update(&self.state.input.filter);
+        match self.config.keybindings.action(key_event) {
+            Some(action) => self.perform(action),
+            None => {
+                if let KeyCode::Char(to_insert) = key_event.code {
+                    self.state.input.enter_char(to_insert);
+                    self.update_filter();
+                }
+            }
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse_event: MouseEvent) {
+        if !self.config.mouse.enable {
+            return;
+        }
+        let position = Position::new(mouse_event.column, mouse_event.row);
+        let application_list = &mut self.state.application_list;
+        match mouse_event.kind {
+            MouseEventKind::ScrollDown => {
+                self.move_selection(|list| list.scroll(Self::SCROLL_ROWS))
             }
-            (_, KeyCode::Backspace) => {
-                self.state.input.delete_char();
-                self.state.application_list.update(&self.state.input.filter);
+            MouseEventKind::ScrollUp => self.move_selection(|list| list.scroll(-Self::SCROLL_ROWS)),
+            MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left)
+                if application_list.scrollbar_contains(position) =>
+            {
+                application_list.jump_to(position.y);
+                self.set_info_to_current_application();
             }
-            (_, KeyCode::Delete) => {
-                self.state.input.right_delete_char();
-                self.state.application_list.update(&self.state.input.filter);
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(row) = application_list.row_at(position) {
+                    let double_click = self.last_click.is_some_and(|(instant, last_row)| {
+                        last_row == row && instant.elapsed() < Self::DOUBLE_CLICK_INTERVAL
+                    });
+                    self.last_click = (!double_click).then(|| (Instant::now(), row));
+                    application_list.select_row(row);
+                    self.set_info_to_current_application();
+                    if double_click {
+                        self.select(false);
+                    }
+                } else {
+                    self.state.input.move_cursor_to(position);
+                }
             }
-            (_, KeyCode::Left) => self.state.input.move_cursor_left(),
-            (_, KeyCode::Right) => self.state.input.move_cursor_right(),
-            (_, KeyCode::Enter) => self.select_application(false),
-            (_, KeyCode::Down | KeyCode::Tab) => self.move_selection_down(),
-            (_, KeyCode::Up | KeyCode::BackTab) => self.move_selection_up(),
-            (_, KeyCode::Esc) => self.mode = RunMode::Exit,
             _ => {}
         }
     }
 
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Select => self.select(false),
+            Action::SelectKeepAlive => self.select(true),
+            Action::Blacklist => self.blacklist_application(),
+            Action::Next => self.move_selection(ApplicationListState::select_next),
+            Action::Previous => self.move_selection(ApplicationListState::select_previous),
+            Action::PageDown => self.move_selection(ApplicationListState::select_next_page),
+            Action::PageUp => self.move_selection(ApplicationListState::select_previous_page),
+            Action::First => self.move_selection(ApplicationListState::select_first),
+            Action::Last => self.move_selection(ApplicationListState::select_last),
+            Action::Exit => self.exit(),
+            Action::DeleteChar => self.edit_filter(InputState::delete_char),
+            Action::DeleteCharForward => self.edit_filter(InputState::right_delete_char),
+            Action::CursorLeft => self.state.input.move_cursor_left(),
+            Action::CursorRight => self.state.input.move_cursor_right(),
+            Action::CursorHome => self.state.input.move_cursor_to_start(),
+            Action::CursorEnd => self.state.input.move_cursor_to_end(),
+            Action::CursorWordLeft => self.state.input.move_cursor_word_left(),
+            Action::CursorWordRight => self.state.input.move_cursor_word_right(),
+            Action::DeleteWord => self.edit_filter(InputState::delete_word),
+            Action::KillToStart => self.edit_filter(InputState::kill_to_start),
+            Action::KillToEnd => self.edit_filter(InputState::kill_to_end),
+            Action::Yank => self.edit_filter(InputState::yank),
+            // never bound, see `Keybindings`
+            Action::Unbind => {}
+        }
+    }
+
+    fn edit_filter(&mut self, edit: fn(&mut InputState)) {
+        edit(&mut self.state.input);
+        self.update_filter();
+    }
+
+    fn update_filter(&mut self) {
+        self.state
+            .application_list
+            .update(&self.state.input.filter, &self.config);
+        self.set_info_to_current_application();
+    }
+
     fn cursor_position(&self, relative_cursor_position: Position) -> Position {
         let icon_x = if self.config.input.icon.is_empty() {
             0
         } else {
-            3 + self.config.input.icon.len() as u16
+            3 + self.config.input.icon.width() as u16
         };
         let default_padding_x = 0u16;
         let input_border_x = 0u16;
@@ -137,58 +331,109 @@ impl Spellbook {
         Position::new(x, y)
     }
 
+    fn exit(&mut self) {
+        let exit_code = if self.args.dmenu && self.selections.is_empty() {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        };
+        self.mode = RunMode::Exit(exit_code);
+    }
+
+    fn select(&mut self, keep_alive: bool) {
+        if self.args.dmenu {
+            self.select_item(keep_alive);
+        } else {
+            self.select_application(keep_alive);
+        }
+    }
+
+    /// Picks the selected item, or the query itself if nothing matches.
+    fn select_item(&mut self, keep_alive: bool) {
+        let selection = match self.state.application_list.selected() {
+            Some(item) if self.args.index => item.id.clone(),
+            Some(item) => item.name.clone(),
+            None if self.args.index => String::from("-1"),
+            None => self.state.input.filter.clone(),
+        };
+        self.selections.push(selection);
+        if !keep_alive {
+            self.mode = RunMode::Exit(ExitCode::SUCCESS);
+        }
+    }
+
     fn select_application(&mut self, keep_alive: bool) {
-        let Some(application) = self.state.application_list.selected() else {
+        let Some(application) = self.state.application_list.selected().cloned() else {
             return;
         };
-        self.state
-            .application_list
-            .increment_launch_count(&application);
-        self.state.application_list.save_db();
+        let mut argv = application.exec.argv(&[]);
         if application.terminal {
-            ratatui::restore();
-            let _ = execvp(&application.filename, application.args.as_slice());
-            return;
-        }
-        match unsafe { fork() } {
-            Ok(ForkResult::Parent { child }) => {
-                if keep_alive {
+            let spawn = keep_alive || matches!(self.config.terminal.mode, TerminalMode::Spawn);
+            match terminal::wrap(&self.config.terminal, &argv) {
+                Some(wrapped) if spawn => argv = wrapped,
+                None if keep_alive => {
+                    let message = String::from("no terminal emulator found, set terminal.command");
+                    self.state.info.update_message(Some(message));
                     return;
                 }
-                loop {
-                    match waitpid(child, Some(WaitPidFlag::WNOHANG)) {
-                        Ok(WaitStatus::Exited(_, _)) => {
-                            ratatui::restore();
-                            exit(0);
-                        }
-                        Err(_) => todo!(),
-                        _ => {
-                            thread::sleep(Duration::from_millis(10));
-                        }
-                    }
+                _ => {
+                    self.replace_with_application(&application, &argv);
+                    return;
                 }
             }
-            Ok(ForkResult::Child) => {
-                let _ = setsid();
-                match unsafe { fork() } {
-                    Ok(ForkResult::Parent { child: _ }) => exit(0),
-                    Ok(ForkResult::Child) => {
-                        let _ = execvp(&application.filename, application.args.as_slice());
-                    }
-                    Err(_) => todo!(),
-                }
+        }
+        match launcher::spawn(&argv) {
+            Ok(launch) => {
+                let pending_launch = PendingLaunch {
+                    launch,
+                    application,
+                    keep_alive,
+                };
+                self.launches
+                    .insert(pending_launch.launch.pid, pending_launch);
             }
-            Err(_) => todo!(),
+            Err(error) => self.state.info.update_message(Some(error.to_string())),
         }
     }
 
-    fn move_selection_down(&mut self) {
-        self.state.application_list.select_next();
-        self.set_info_to_current_application();
+    fn finish_launch(&mut self, status: WaitStatus) {
+        let Some(pending_launch) = status.pid().and_then(|pid| self.launches.remove(&pid)) else {
+            return;
+        };
+        let PendingLaunch {
+            launch,
+            application,
+            keep_alive,
+        } = pending_launch;
+        if let Err(error) = launch.finish(status) {
+            self.state.info.update_message(Some(error.to_string()));
+            return;
+        }
+        self.state.application_list.record_launch(&application);
+        self.state.application_list.save_db();
+        if !keep_alive {
+            self.mode = RunMode::Exit(ExitCode::SUCCESS);
+        }
     }
 
-    fn move_selection_up(&mut self) {
-        self.state.application_list.select_previous();
+    /// Runs a terminal application in place of spellbook, resuming if that fails.
+    fn replace_with_application(&mut self, application: &Application, argv: &[CString]) {
+        // the launch has to be saved up front, as a successful exec never returns
+        self.state.application_list.record_launch(application);
+        self.state.application_list.save_db();
+        tui::restore();
+        let error = launcher::replace(argv);
+        let _ = tui::resume();
+        self.clear_terminal = true;
+        self.state
+            .application_list
+            .set_db_entry(application, &application.db_entry);
+        self.state.application_list.save_db();
+        self.state.info.update_message(Some(error.to_string()));
+    }
+
+    fn move_selection(&mut self, select: fn(&mut ApplicationListState)) {
+        select(&mut self.state.application_list);
         self.set_info_to_current_application();
     }
 
@@ -196,18 +441,31 @@ impl Spellbook {
         let Some(application) = self.state.application_list.selected() else {
             return;
         };
-        let message = application.comment;
+        let message = match self.state.application_list.selected_match() {
+            Some(search_match) if search_match.field != SearchField::Name => Some(format!(
+                "{}: {}",
+                search_match.field.label(),
+                search_match.text(application)
+            )),
+            _ => application
+                .comment
+                .clone()
+                .or_else(|| application.generic_name.clone()),
+        };
         self.state.info.update_message(message);
     }
 
     fn blacklist_application(&mut self) {
+        if self.args.dmenu {
+            return;
+        }
         let application_list = &mut self.state.application_list;
-        let Some(application) = application_list.selected() else {
+        let Some(application) = application_list.selected().cloned() else {
             return;
         };
         application_list.blacklist(&application);
         application_list.save_db();
-        application_list.update(&self.state.input.filter);
+        application_list.update(&self.state.input.filter, &self.config);
         let message = format!("blacklisted application '{}'", application.name);
         self.state.info.update_message(Some(message));
     }
@@ -228,7 +486,8 @@ impl Widget for &mut Spellbook {
 
         let input_block = Block::new()
             .borders(Borders::NONE)
-            .border_set(symbols::border::PROPORTIONAL_WIDE);
+            .border_set(self.theme.border_set)
+            .border_style(self.theme.border);
         let counter_area_constraint = if self.config.counter.enable {
             Constraint::Length(9)
         } else {
@@ -240,7 +499,7 @@ impl Widget for &mut Spellbook {
         Widget::render(input_block, input_and_counter_area, buf);
 
         StatefulWidget::render(
-            Input::new(&self.config),
+            Input::new(&self.config, &self.theme),
             input_area,
             buf,
             &mut self.state.input,
@@ -248,24 +507,27 @@ impl Widget for &mut Spellbook {
         Widget::render(
             Counter::new(
                 &self.config,
-                self.state.application_list.filtered_applications.len(),
+                &self.theme,
+                self.state.application_list.filtered_len(),
                 self.state.application_list.non_blacklisted_applications_len,
+                self.state.application_list.loading,
             ),
             counter_area,
             buf,
         );
         StatefulWidget::render(
-            ApplicationList::new(&self.config, &self.state.input.filter),
+            ApplicationList::new(&self.config, &self.theme, &self.state.input.filter),
             list_area,
             buf,
             &mut self.state.application_list,
         );
         let info_block = Block::new()
             .borders(Borders::all())
-            .border_set(symbols::border::PROPORTIONAL_WIDE);
+            .border_set(self.theme.border_set)
+            .border_style(self.theme.border);
         Widget::render(info_block, info_area, buf);
         StatefulWidget::render(
-            Info::new(&self.config),
+            Info::new(&self.config, &self.theme),
             info_area.inner(Margin::new(1, 1)),
             buf,
             &mut self.state.info,
@@ -273,9 +535,19 @@ impl Widget for &mut Spellbook {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct SpellbookState {
     pub input: InputState,
     pub application_list: ApplicationListState,
     pub info: InfoState,
 }
+
+impl SpellbookState {
+    pub fn new(application_list: ApplicationListState) -> Self {
+        Self {
+            input: InputState::default(),
+            application_list,
+            info: InfoState::default(),
+        }
+    }
+}
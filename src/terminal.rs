@@ -0,0 +1,107 @@
+use std::{env, ffi::CString};
+
+use crate::{application::is_executable, config::TerminalConfig, exec::Exec};
+
+/// Placeholder in `terminal.command` for the command to run.
+const CMD_PLACEHOLDER: &str = "{cmd}";
+
+/// Terminal emulators tried in order when neither `terminal.command` nor `$TERMINAL` is set.
+const KNOWN_TERMINALS: [&str; 11] = [
+    "foot {cmd}",
+    "kitty {cmd}",
+    "alacritty -e {cmd}",
+    "wezterm start -- {cmd}",
+    "ghostty -e {cmd}",
+    "gnome-terminal -- {cmd}",
+    "konsole -e {cmd}",
+    "xfce4-terminal -x {cmd}",
+    "st -e {cmd}",
+    "urxvt -e {cmd}",
+    "xterm -e {cmd}",
+];
+
+/// Wraps `argv` in the configured or detected terminal emulator.
+///
+/// The command is split like an `Exec` key, so arguments containing spaces can be quoted.
+pub fn wrap(config: &TerminalConfig, argv: &[CString]) -> Option<Vec<CString>> {
+    let template = command_template(config)?;
+    let mut wrapped = Vec::new();
+    let mut has_placeholder = false;
+    for part in Exec::split(&template)? {
+        if part == CMD_PLACEHOLDER {
+            wrapped.extend_from_slice(argv);
+            has_placeholder = true;
+        } else {
+            wrapped.push(CString::new(part).ok()?);
+        }
+    }
+    if !has_placeholder {
+        wrapped.extend_from_slice(argv);
+    }
+    Some(wrapped)
+}
+
+fn command_template(config: &TerminalConfig) -> Option<String> {
+    if !config.command.is_empty() {
+        return Some(config.command.clone());
+    }
+    if let Ok(terminal) = env::var("TERMINAL")
+        && !terminal.is_empty()
+    {
+        return match KNOWN_TERMINALS
+            .iter()
+            .find(|known| known.split_whitespace().next() == Some(terminal.as_str()))
+        {
+            Some(known) => Some(known.to_string()),
+            None => Some(format!("{} -e {}", terminal, CMD_PLACEHOLDER)),
+        };
+    }
+    KNOWN_TERMINALS
+        .iter()
+        .find(|known| known.split_whitespace().next().is_some_and(is_executable))
+        .map(|known| known.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap_command(command: &str) -> Option<Vec<String>> {
+        let config = TerminalConfig {
+            command: command.to_string(),
+            ..Default::default()
+        };
+        let argv = [
+            CString::new("htop").unwrap(),
+            CString::new("-d 10").unwrap(),
+        ];
+        let wrapped = wrap(&config, &argv)?;
+        Some(
+            wrapped
+                .into_iter()
+                .map(|arg| arg.into_string().unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn keeps_quoted_arguments_together() {
+        assert_eq!(
+            wrap_command(r#"foot --title "spellbook app" -e {cmd}"#).unwrap(),
+            ["foot", "--title", "spellbook app", "-e", "htop", "-d 10"]
+        );
+        assert_eq!(
+            wrap_command(r#"kitty --class "float term" --override 'x=%f'"#).unwrap(),
+            [
+                "kitty",
+                "--class",
+                "float term",
+                "--override",
+                "'x=%f'",
+                "htop",
+                "-d 10"
+            ]
+        );
+        assert_eq!(wrap_command(r#"foot --title "spellbook {cmd}"#), None);
+    }
+}
@@ -0,0 +1,221 @@
+use std::env;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    symbols::border,
+};
+
+use crate::config::{BorderType, StyleConfig, ThemeConfig, ThemeMode};
+
+/// Built-in styles the `[theme]` config is applied to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    Dark,
+    Light,
+    /// only bold and reverse, for `NO_COLOR`
+    Monochrome,
+}
+
+impl Palette {
+    /// Picks the palette for `mode`, using the terminal's `background` color in auto mode.
+    pub fn new(mode: ThemeMode, background: Option<(f32, f32, f32)>, no_color: bool) -> Self {
+        if no_color {
+            return Self::Monochrome;
+        }
+        match mode {
+            ThemeMode::Light => Self::Light,
+            ThemeMode::Dark => Self::Dark,
+            ThemeMode::Auto => match background {
+                Some((r, g, b)) if 0.2126 * r + 0.7152 * g + 0.0722 * b > 0.5 => Self::Light,
+                _ => Self::Dark,
+            },
+        }
+    }
+}
+
+/// Whether the background color is needed to pick the palette.
+pub fn needs_background(config: &ThemeConfig, no_color: bool) -> bool {
+    config.mode == ThemeMode::Auto && !no_color
+}
+
+/// Whether `NO_COLOR` is set, see <https://no-color.org>.
+pub fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Styles of every widget, with the `[theme]` config applied.
+#[derive(Debug)]
+pub struct Theme {
+    pub list: Style,
+    pub selected: Style,
+    pub highlight: Style,
+    pub placeholder: Style,
+    pub counter: Style,
+    pub info: Style,
+    pub border: Style,
+    pub border_set: border::Set<'static>,
+    pub scrollbar: Style,
+    /// whether application icons keep their own colors
+    pub icon_colors: bool,
+}
+
+impl Theme {
+    pub fn new(config: &ThemeConfig, palette: Palette) -> Self {
+        let builtin = Self::builtin(palette);
+        Self {
+            list: patch(builtin.list, &config.list),
+            selected: patch(builtin.selected, &config.selected),
+            highlight: patch(builtin.highlight, &config.highlight),
+            placeholder: patch(builtin.placeholder, &config.placeholder),
+            counter: patch(builtin.counter, &config.counter),
+            info: patch(builtin.info, &config.info),
+            border: patch(builtin.border, &config.border),
+            border_set: match config.border_type {
+                BorderType::Plain => border::PLAIN,
+                BorderType::Rounded => border::ROUNDED,
+                BorderType::Double => border::DOUBLE,
+                BorderType::Thick => border::THICK,
+                BorderType::ProportionalWide => border::PROPORTIONAL_WIDE,
+                BorderType::ProportionalTall => border::PROPORTIONAL_TALL,
+            },
+            scrollbar: patch(builtin.scrollbar, &config.scrollbar),
+            icon_colors: builtin.icon_colors,
+        }
+    }
+
+    fn builtin(palette: Palette) -> Self {
+        let dark = Self {
+            list: Style::new().fg(Color::White).bg(Color::Reset),
+            selected: Style::new().fg(Color::Gray).bg(Color::Black).not_reversed(),
+            highlight: Style::new().bg(Color::DarkGray).bold(),
+            placeholder: Style::new().fg(Color::DarkGray).italic(),
+            counter: Style::new(),
+            info: Style::new(),
+            border: Style::new(),
+            border_set: border::PROPORTIONAL_WIDE,
+            scrollbar: Style::new().fg(Color::White),
+            icon_colors: true,
+        };
+        match palette {
+            Palette::Dark => dark,
+            Palette::Light => Self {
+                list: Style::new().fg(Color::Black).bg(Color::Reset),
+                selected: Style::new().fg(Color::Black).bg(Color::Gray).not_reversed(),
+                highlight: Style::new().fg(Color::Blue).bold(),
+                scrollbar: Style::new().fg(Color::DarkGray),
+                ..dark
+            },
+            Palette::Monochrome => Self {
+                list: Style::new(),
+                selected: Style::new().reversed(),
+                highlight: Style::new().bold(),
+                placeholder: Style::new(),
+                scrollbar: Style::new(),
+                icon_colors: false,
+                ..dark
+            },
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::builtin(Palette::Dark)
+    }
+}
+
+/// Overrides whatever `config` sets, keeping the rest of `style`.
+fn patch(mut style: Style, config: &StyleConfig) -> Style {
+    style.fg = config.fg.or(style.fg);
+    style.bg = config.bg.or(style.bg);
+    if let Some(modifiers) = &config.modifiers {
+        let modifiers = modifiers
+            .iter()
+            .fold(Modifier::empty(), |all, &modifier| all | modifier.into());
+        style.add_modifier = modifiers;
+        style.sub_modifier -= modifiers;
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme(toml: &str) -> Result<Theme, String> {
+        toml::from_str::<ThemeConfig>(toml)
+            .map(|config| Theme::new(&config, Palette::Dark))
+            .map_err(|error| error.message().to_string())
+    }
+
+    #[test]
+    fn patches_only_what_is_set() {
+        let theme = theme(
+            r##"
+            selected = { bg = "#1e1e2e" }
+            highlight = { fg = 208, modifiers = ["underlined"] }
+            border_type = "rounded"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(
+            theme.selected,
+            Style::new()
+                .fg(Color::Gray)
+                .bg(Color::Rgb(0x1e, 0x1e, 0x2e))
+                .not_reversed()
+        );
+        assert_eq!(
+            theme.highlight,
+            Style::new()
+                .fg(Color::Indexed(208))
+                .bg(Color::DarkGray)
+                .underlined()
+        );
+        assert_eq!(theme.border_set, border::ROUNDED);
+    }
+
+    #[test]
+    fn picks_the_palette_from_the_background() {
+        let light = Some((0.98, 0.96, 0.9));
+        let dark = Some((0.1, 0.1, 0.15));
+        assert_eq!(Palette::new(ThemeMode::Auto, light, false), Palette::Light);
+        assert_eq!(Palette::new(ThemeMode::Auto, dark, false), Palette::Dark);
+        assert_eq!(Palette::new(ThemeMode::Auto, None, false), Palette::Dark);
+        assert_eq!(Palette::new(ThemeMode::Dark, light, false), Palette::Dark);
+        assert_eq!(Palette::new(ThemeMode::Light, dark, false), Palette::Light);
+        assert_eq!(
+            Palette::new(ThemeMode::Auto, light, true),
+            Palette::Monochrome
+        );
+        assert_eq!(
+            Palette::new(ThemeMode::Light, light, true),
+            Palette::Monochrome
+        );
+    }
+
+    #[test]
+    fn monochrome_uses_only_bold_and_reverse() {
+        let theme = Theme::new(&ThemeConfig::default(), Palette::Monochrome);
+        for style in [
+            theme.list,
+            theme.selected,
+            theme.highlight,
+            theme.placeholder,
+        ] {
+            assert_eq!((style.fg, style.bg), (None, None));
+            assert!((Modifier::BOLD | Modifier::REVERSED).contains(style.add_modifier));
+        }
+        assert!(!theme.icon_colors);
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        assert_eq!(
+            theme(r#"list = { fg = "chartreuse" }"#).err(),
+            Some(String::from(
+                "invalid color `chartreuse`, expected a name like `light_blue`, an index like `208` or `#rrggbb`"
+            ))
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    os::fd::AsFd,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use nix::{
+    poll::{PollFd, PollFlags, poll},
+    sys::termios::{FlushArg, tcflush},
+};
+use ratatui::{Terminal, backend::CrosstermBackend};
+
+pub type Tui = Terminal<CrosstermBackend<Box<dyn Write>>>;
+
+static DRAW_ON_TTY: AtomicBool = AtomicBool::new(false);
+static MOUSE_CAPTURE: AtomicBool = AtomicBool::new(false);
+
+/// Like [`ratatui::init`], but optionally draws on `/dev/tty` to keep stdout free for output.
+pub fn init(draw_on_tty: bool) -> io::Result<Tui> {
+    DRAW_ON_TTY.store(draw_on_tty, Ordering::Relaxed);
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        hook(info);
+    }));
+    resume()?;
+    Terminal::new(CrosstermBackend::new(output()?))
+}
+
+/// Re-enters raw mode and the alternate screen after [`restore`].
+pub fn resume() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(output()?, EnterAlternateScreen)?;
+    if MOUSE_CAPTURE.load(Ordering::Relaxed) {
+        execute!(output()?, EnableMouseCapture)?;
+    }
+    Ok(())
+}
+
+/// Like [`ratatui::restore`], for whichever output [`init`] was drawing on.
+pub fn restore() {
+    let _ = disable_raw_mode();
+    if let Ok(mut output) = output() {
+        if MOUSE_CAPTURE.load(Ordering::Relaxed) {
+            let _ = execute!(output, DisableMouseCapture);
+        }
+        let _ = execute!(output, LeaveAlternateScreen);
+    }
+}
+
+/// Captures mouse events, leaving text selection to the terminal while disabled.
+pub fn set_mouse_capture(enable: bool) -> io::Result<()> {
+    if MOUSE_CAPTURE.swap(enable, Ordering::Relaxed) == enable {
+        return Ok(());
+    }
+    if enable {
+        execute!(output()?, EnableMouseCapture)
+    } else {
+        execute!(output()?, DisableMouseCapture)
+    }
+}
+
+/// how long to wait for the terminal to answer [`query_background`]
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Asks the terminal for its background color with OSC 11, as RGB in `0.0..=1.0`.
+///
+/// Has to be called before anything else reads terminal input, as that would swallow the answer.
+/// The query is followed by a device attributes request, which every terminal answers,
+/// so terminals without OSC 11 support do not hold up startup until the timeout.
+pub fn query_background() -> Option<(f32, f32, f32)> {
+    let mut tty = File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    enable_raw_mode().ok()?;
+    let response = read_query_response(&mut tty);
+    // drops anything that arrived late, so it is not read as key presses
+    let _ = tcflush(&tty, FlushArg::TCIFLUSH);
+    let _ = disable_raw_mode();
+    parse_background(&response?)
+}
+
+fn read_query_response(tty: &mut File) -> Option<Vec<u8>> {
+    tty.write_all(b"\x1b]11;?\x1b\\\x1b[c").ok()?;
+    let start = Instant::now();
+    let mut response = Vec::new();
+    let mut chunk = [0; 256];
+    // the device attributes answer looks like `ESC [ ? 62 ; 22 c`
+    while !response.ends_with(b"c") || !response.windows(3).any(|w| w == b"\x1b[?") {
+        let remaining = QUERY_TIMEOUT.checked_sub(start.elapsed())?;
+        let timeout = u16::try_from(remaining.as_millis()).unwrap_or(u16::MAX);
+        let mut fds = [PollFd::new(tty.as_fd(), PollFlags::POLLIN)];
+        if poll(&mut fds, timeout).ok()? == 0 {
+            return None;
+        }
+        let read = tty.read(&mut chunk).ok()?;
+        response.extend_from_slice(&chunk[..read]);
+    }
+    Some(response)
+}
+
+/// Parses an OSC 11 answer like `ESC ] 11 ; rgb:ffff/f0f0/e0e0 ESC \`, with 1 to 4 hex digits per channel.
+fn parse_background(response: &[u8]) -> Option<(f32, f32, f32)> {
+    let response = String::from_utf8_lossy(response);
+    let (_, color) = response.split_once("]11;rgb:")?;
+    let color = color.split(['\x1b', '\x07']).next()?;
+    let mut channels = color.split('/').map(|channel| {
+        let value = u16::from_str_radix(channel, 16).ok()?;
+        let max = 16u32.checked_pow(u32::try_from(channel.len()).ok()?)? - 1;
+        (channel.len() <= 4).then(|| f32::from(value) / max as f32)
+    });
+    let (Some(Some(r)), Some(Some(g)), Some(Some(b)), None) = (
+        channels.next(),
+        channels.next(),
+        channels.next(),
+        channels.next(),
+    ) else {
+        return None;
+    };
+    Some((r, g, b))
+}
+
+fn output() -> io::Result<Box<dyn Write>> {
+    if DRAW_ON_TTY.load(Ordering::Relaxed) {
+        Ok(Box::new(File::options().write(true).open("/dev/tty")?))
+    } else {
+        Ok(Box::new(io::stdout()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_background_answers() {
+        assert_eq!(
+            parse_background(b"\x1b]11;rgb:ffff/8080/0000\x1b\\\x1b[?62;22c"),
+            Some((1.0, 32896.0 / 65535.0, 0.0))
+        );
+        assert_eq!(
+            parse_background(b"\x1b]11;rgb:f/0/8\x07"),
+            Some((1.0, 0.0, 8.0 / 15.0))
+        );
+        assert_eq!(parse_background(b"\x1b[?62;22c"), None);
+        assert_eq!(parse_background(b"\x1b]11;rgb:ffff/ffff\x1b\\"), None);
+        assert_eq!(parse_background(b"\x1b]11;rgb:fffff/0/0\x1b\\"), None);
+    }
+}
@@ -1,8 +1,13 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Layout, Rect},
-    style::{Color, Style},
-    text::Line,
+    layout::{Constraint, Layout, Position, Rect},
+    text::{Line, Span},
     widgets::{
         List, ListDirection, ListState, Scrollbar, ScrollbarOrientation, ScrollbarState,
         StatefulWidget,
@@ -11,18 +16,26 @@ use ratatui::{
 
 use crate::{
     application::Application,
-    config::Config,
+    config::{ApplicationListActions, Config, SearchConfig, SearchMode},
     db::{Db, DbEntry},
+    search::{self, SearchMatch},
+    theme::Theme,
+    xdg::{self, DesktopFile},
 };
 
 pub struct ApplicationList<'a> {
     config: &'a Config,
+    theme: &'a Theme,
     filter: &'a str,
 }
 
 impl<'a> ApplicationList<'a> {
-    pub fn new(config: &'a Config, filter: &'a str) -> Self {
-        Self { config, filter }
+    pub fn new(config: &'a Config, theme: &'a Theme, filter: &'a str) -> Self {
+        Self {
+            config,
+            theme,
+            filter,
+        }
     }
 }
 
@@ -32,41 +45,60 @@ impl StatefulWidget for ApplicationList<'_> {
         let [_, scrollbar_area] =
             Layout::horizontal([Constraint::Min(1), Constraint::Max(1)]).areas(area);
 
+        let height = usize::from(area.height);
+        state.area = area;
+        state.scrollbar_area = if self.config.scrollbar.enable {
+            scrollbar_area
+        } else {
+            Rect::default()
+        };
+        let len = state.filtered.len();
+        let selected = state
+            .list
+            .selected()
+            .unwrap_or(0)
+            .min(len.saturating_sub(1));
+        *state.list.selected_mut() = Some(selected);
+        // keep the selection in view and the list filled, like `List` would
+        let mut offset = state.list.offset();
+        if selected < offset {
+            offset = selected;
+        } else if height > 0 && selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+        offset = offset.min(len.saturating_sub(height));
+        *state.list.offset_mut() = offset;
+
+        // only the visible window is turned into lines
         let mut highlighted_applications = Vec::new();
-        for application in &state.filtered_applications {
+        for &i in state.filtered.iter().skip(offset).take(height) {
+            let application = &state.applications[i];
             let mut highlight_spans = Vec::new();
+            if application.parent.is_some()
+                && matches!(
+                    self.config.application_list.actions,
+                    ApplicationListActions::Grouped
+                )
+            {
+                highlight_spans.push(Span::raw("  "));
+            }
             if self.config.application_list.display_icons {
-                highlight_spans.push(application.get_icon());
+                highlight_spans.push(application.get_icon(self.theme.icon_colors));
             }
-            highlight_spans.append(&mut application.get_highlighted_name(self.filter));
+            highlight_spans.append(&mut application.get_highlighted_name(
+                self.filter,
+                &self.config.search,
+                self.theme.highlight,
+            ));
             highlighted_applications.push(Line::from(highlight_spans));
         }
 
-        let (fg_color, bg_color, highlight_color) = (Color::White, Color::Reset, Color::Black);
         let list = List::new(highlighted_applications)
-            .style(Style::new().fg(fg_color).bg(bg_color))
-            .highlight_style(
-                Style::new()
-                    .fg(Color::Gray)
-                    .bg(highlight_color)
-                    .not_reversed(),
-            )
+            .style(self.theme.list)
+            .highlight_style(self.theme.selected)
             .direction(ListDirection::TopToBottom);
-
-        if state.list.selected().is_none() {
-            *state.list.selected_mut() = Some(0);
-        }
-
-        if list.len() <= area.height.into() {
-            *state.list.offset_mut() = 0;
-        }
-
-        while list.len() > area.height.into()
-            && list.len() - state.list.offset() < area.height.into()
-        {
-            *state.list.offset_mut() -= 1;
-        }
-        StatefulWidget::render(list, area, buf, &mut state.list);
+        let mut visible = ListState::default().with_selected(Some(selected - offset));
+        StatefulWidget::render(list, area, buf, &mut visible);
 
         if self.config.scrollbar.enable {
             let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
@@ -74,13 +106,12 @@ impl StatefulWidget for ApplicationList<'_> {
                 .end_symbol(None)
                 .track_symbol(None)
                 .thumb_symbol("┃")
-                .style(Style::new().fg(fg_color));
-            let scrollable_range =
-                (state.filtered_applications.len() as i16 - area.height as i16).max(0);
+                .style(self.theme.scrollbar);
+            let scrollable_range = len.saturating_sub(height);
             let mut scrollbar_state = state
                 .scrollbar
-                .content_length(scrollable_range as usize)
-                .position(state.list.offset());
+                .content_length(scrollable_range)
+                .position(offset);
             StatefulWidget::render(scrollbar, scrollbar_area, buf, &mut scrollbar_state);
         }
     }
@@ -88,29 +119,171 @@ impl StatefulWidget for ApplicationList<'_> {
 
 #[derive(Debug)]
 pub struct ApplicationListState {
-    pub filtered_applications: Vec<Application>,
+    /// indices into `applications` of the entries matching the filter, in display order
+    filtered: Vec<usize>,
+    search_matches: Vec<Option<SearchMatch>>,
+    /// filter and search settings `filtered` was computed for, if still valid for narrowing
+    previous_filter: Option<(String, SearchConfig)>,
     pub applications: Vec<Application>,
     pub non_blacklisted_applications_len: usize,
+    /// desktop file ID to path, as of the last scan
+    desktop_files: HashMap<String, PathBuf>,
+    pub loading: bool,
+    /// time spent sorting while loading
+    pub loading_sort_duration: Duration,
+    /// changes reported while loading, applied once loading finished
+    pending_changes: Vec<PathBuf>,
     list: ListState,
     scrollbar: ScrollbarState,
+    /// areas of the last render, to scroll by a page and map mouse clicks
+    area: Rect,
+    scrollbar_area: Rect,
 }
 
 impl ApplicationListState {
-    pub fn update(&mut self, filter: &str) {
-        self.filtered_applications = self
-            .applications
-            .clone()
+    /// Filters the applications, only searching the previous matches if `filter` extends the previous filter.
+    pub fn update(&mut self, filter: &str, config: &Config) {
+        let narrowing = config.search.mode != SearchMode::Exact
+            && self
+                .previous_filter
+                .as_ref()
+                .is_some_and(|(previous, search)| {
+                    search::extends(previous, filter) && *search == config.search
+                });
+        let candidates = if narrowing {
+            let mut candidates = std::mem::take(&mut self.filtered);
+            candidates.sort_unstable();
+            candidates
+        } else {
+            (0..self.applications.len())
+                .filter(|&i| !self.applications[i].db_entry.blacklisted)
+                .collect()
+        };
+        let query = search::Query::new(filter, &config.search);
+        let mut filtered = candidates
             .into_iter()
-            .filter(|entry| {
-                entry.name.to_lowercase().contains(&filter.to_lowercase())
-                    && !entry.db_entry.blacklisted
+            .filter_map(|i| {
+                if filter.is_empty() {
+                    return Some((i, None));
+                }
+                let search_match = search::search(&self.applications[i], &query, &config.search)?;
+                Some((i, Some(search_match)))
             })
-            .collect();
+            .collect::<Vec<(usize, Option<SearchMatch>)>>();
+        // The candidates are in the configured order, which the stable sort keeps among equal scores.
+        filtered.sort_by_key(|(_, search_match)| {
+            std::cmp::Reverse(search_match.as_ref().map(|search_match| search_match.score))
+        });
+        (self.filtered, self.search_matches) = filtered.into_iter().unzip();
+        self.previous_filter = Some((filter.to_string(), config.search.clone()));
+    }
+
+    pub fn filtered_len(&self) -> usize {
+        self.filtered.len()
+    }
+
+    /// Re-parses new and `changed` desktop files and drops removed ones,
+    /// keeping the filter and, if it is still listed, the selected application.
+    pub fn refresh(&mut self, changed: &[PathBuf], filter: &str, config: &Config) {
+        if self.loading {
+            self.pending_changes.extend_from_slice(changed);
+            return;
+        }
+        let selected = self.selected_key();
+        let desktop_files = xdg::desktop_files();
+        let (stale, unchanged): (Vec<DesktopFile>, Vec<DesktopFile>) =
+            desktop_files.into_iter().partition(|desktop_file| {
+                self.desktop_files.get(&desktop_file.id) != Some(&desktop_file.path)
+                    || changed.contains(&desktop_file.path)
+            });
+        let unchanged_ids = unchanged
+            .iter()
+            .map(|desktop_file| desktop_file.id.as_str())
+            .collect::<HashSet<&str>>();
+        self.applications
+            .retain(|application| unchanged_ids.contains(application.id.as_str()));
+        self.applications
+            .extend(Application::find_all(&stale, &config.application_list));
+        self.desktop_files = Self::desktop_file_paths(unchanged.into_iter().chain(stale));
+        self.rebuild(selected, filter, config);
+    }
+
+    /// Adds a batch of applications from the background loader.
+    pub fn extend(&mut self, applications: Vec<Application>, filter: &str, config: &Config) {
+        let selected = self.selected_key();
+        self.applications.extend(applications);
+        self.rebuild(selected, filter, config);
+    }
+
+    pub fn finish_loading(
+        &mut self,
+        desktop_files: Vec<DesktopFile>,
+        filter: &str,
+        config: &Config,
+    ) {
+        self.desktop_files = Self::desktop_file_paths(desktop_files.into_iter());
+        self.loading = false;
+        let pending_changes = std::mem::take(&mut self.pending_changes);
+        if !pending_changes.is_empty() {
+            self.refresh(&pending_changes, filter, config);
+        }
+    }
+
+    /// ID and untranslated name of the selected application, which identify it across rebuilds.
+    fn selected_key(&self) -> Option<(String, String)> {
+        self.selected().map(|application| {
+            (
+                application.id.clone(),
+                application.untranslated_name.clone(),
+            )
+        })
+    }
+
+    /// Re-sorts and re-filters all applications, selecting `selected` again if it is still listed.
+    fn rebuild(&mut self, selected: Option<(String, String)>, filter: &str, config: &Config) {
+        let start = Instant::now();
+        self.sort(config);
+        if self.loading {
+            self.loading_sort_duration += start.elapsed();
+        }
+        self.non_blacklisted_applications_len = self
+            .applications
+            .iter()
+            .filter(|application| !application.db_entry.blacklisted)
+            .count();
+        self.update(filter, config);
+        if let Some((id, untranslated_name)) = selected
+            && let Some(index) = self.filtered.iter().position(|&i| {
+                let application = &self.applications[i];
+                application.id == id && application.untranslated_name == untranslated_name
+            })
+        {
+            self.list.select(Some(index));
+        }
     }
 
-    pub fn selected(&self) -> Option<Application> {
+    fn desktop_file_paths(
+        desktop_files: impl Iterator<Item = DesktopFile>,
+    ) -> HashMap<String, PathBuf> {
+        desktop_files
+            .map(|desktop_file| (desktop_file.id, desktop_file.path))
+            .collect()
+    }
+
+    /// Sorts the applications, which has to be followed by an [`ApplicationListState::update`].
+    pub fn sort(&mut self, config: &Config) {
+        Application::sort(&mut self.applications, &config.application_list);
+        self.previous_filter = None;
+    }
+
+    pub fn selected(&self) -> Option<&Application> {
+        let i = self.list.selected()?;
+        self.filtered.get(i).map(|&i| &self.applications[i])
+    }
+
+    pub fn selected_match(&self) -> Option<&SearchMatch> {
         let i = self.list.selected()?;
-        Some(self.filtered_applications[i].clone())
+        self.search_matches.get(i)?.as_ref()
     }
 
     pub fn select_previous(&mut self) {
@@ -119,58 +292,231 @@ impl ApplicationListState {
 
     pub fn select_next(&mut self) {
         if let Some(i) = self.list.selected()
-            && i < self.filtered_applications.len() - 1
+            && i + 1 < self.filtered.len()
         {
             self.list.scroll_down_by(1);
         }
     }
 
-    pub fn increment_launch_count(&mut self, filtered_application: &Application) {
+    pub fn select_next_page(&mut self) {
+        let i = self.list.selected().unwrap_or(0) + self.page_height();
+        self.list
+            .select(Some(i.min(self.filtered.len().saturating_sub(1))));
+    }
+
+    pub fn select_previous_page(&mut self) {
+        let i = self.list.selected().unwrap_or(0);
+        self.list.select(Some(i.saturating_sub(self.page_height())));
+    }
+
+    fn page_height(&self) -> usize {
+        usize::from(self.area.height).max(1)
+    }
+
+    /// Index into the filtered list of the row at `position`, if there is one.
+    pub fn row_at(&self, position: Position) -> Option<usize> {
+        if !self.area.contains(position) || self.scrollbar_area.contains(position) {
+            return None;
+        }
+        let i = self.list.offset() + usize::from(position.y - self.area.y);
+        (i < self.filtered.len()).then_some(i)
+    }
+
+    pub fn scrollbar_contains(&self, position: Position) -> bool {
+        self.scrollbar_area.contains(position)
+    }
+
+    pub fn select_row(&mut self, i: usize) {
+        self.list.select(Some(i));
+    }
+
+    /// Scrolls the view by `rows`, dragging the selection along only when it would leave the view.
+    pub fn scroll(&mut self, rows: isize) {
+        let height = self.page_height();
+        let max_offset = self.filtered.len().saturating_sub(height);
+        let offset = self
+            .list
+            .offset()
+            .saturating_add_signed(rows)
+            .min(max_offset);
+        *self.list.offset_mut() = offset;
+        let selected = self.list.selected().unwrap_or(0);
+        self.list
+            .select(Some(selected.clamp(offset, offset + height - 1)));
+    }
+
+    /// Selects the row proportional to where `y` lies on the scrollbar.
+    pub fn jump_to(&mut self, y: u16) {
+        let track = usize::from(self.scrollbar_area.height.saturating_sub(1)).max(1);
+        let position = usize::from(y.saturating_sub(self.scrollbar_area.y)).min(track);
+        let last = self.filtered.len().saturating_sub(1);
+        self.list
+            .select(Some((position * last + track / 2) / track));
+    }
+
+    pub fn select_first(&mut self) {
+        self.list.select(Some(0));
+    }
+
+    pub fn select_last(&mut self) {
+        self.list
+            .select(Some(self.filtered.len().saturating_sub(1)));
+    }
+
+    pub fn record_launch(&mut self, filtered_application: &Application) {
         self.matched_applications_mut(filtered_application)
-            .for_each(|application| application.db_entry.launch_count += 1)
+            .for_each(|application| application.db_entry.record_launch())
+    }
+
+    pub fn set_db_entry(&mut self, filtered_application: &Application, db_entry: &DbEntry) {
+        self.matched_applications_mut(filtered_application)
+            .for_each(|application| application.db_entry = db_entry.clone());
+        self.previous_filter = None;
     }
 
     pub fn blacklist(&mut self, filtered_application: &Application) {
         self.matched_applications_mut(filtered_application)
             .for_each(|application| application.db_entry.blacklisted = true);
         self.non_blacklisted_applications_len -= 1;
+        self.previous_filter = None;
     }
 
     fn matched_applications_mut(
         &mut self,
         filtered_application: &Application,
     ) -> impl Iterator<Item = &mut Application> {
-        self.applications
-            .iter_mut()
-            .filter(|application| application.name == filtered_application.name)
+        self.applications.iter_mut().filter(|application| {
+            application.untranslated_name == filtered_application.untranslated_name
+        })
     }
 
     pub fn save_db(&self) {
+        Db::save_to_disk(self.merged_db_entries(Db::load().entries));
+    }
+
+    /// The entries of the loaded applications, which take precedence even when empty,
+    /// plus those on disk of applications that are hidden or not loaded yet.
+    fn merged_db_entries(&self, on_disk: Vec<DbEntry>) -> Vec<DbEntry> {
+        let loaded = self
+            .applications
+            .iter()
+            .map(|application| application.db_entry.name.as_str())
+            .collect::<HashSet<&str>>();
         let mut entries = self
             .applications
             .iter()
-            .map(|entry| entry.db_entry.clone())
+            .map(|application| application.db_entry.clone())
+            .chain(
+                on_disk
+                    .into_iter()
+                    .filter(|db_entry| !loaded.contains(db_entry.name.as_str())),
+            )
+            .filter(|db_entry| !db_entry.is_empty())
             .collect::<Vec<DbEntry>>();
         entries.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
-        Db::save_to_disk(entries);
+        entries.dedup_by(|a, b| a.name == b.name);
+        entries
     }
 }
 
-impl Default for ApplicationListState {
-    fn default() -> Self {
-        let applications = Application::find_all();
-        let filtered_applications = applications
-            .clone()
+impl ApplicationListState {
+    /// An empty list, filled by [`ApplicationListState::extend`] while loading.
+    pub fn new() -> Self {
+        Self {
+            loading: true,
+            ..Self::with_applications(Vec::new())
+        }
+    }
+
+    /// Plain items for dmenu mode, kept in input order.
+    pub fn from_items(items: Vec<String>) -> Self {
+        let applications = items
             .into_iter()
-            .filter(|application| !application.db_entry.blacklisted)
-            .collect::<Vec<Application>>();
-        let non_blacklisted_applications_len = filtered_applications.len();
+            .enumerate()
+            .map(|(index, item)| Application::from_item(index, item))
+            .collect();
+        Self::with_applications(applications)
+    }
+
+    fn with_applications(applications: Vec<Application>) -> Self {
+        let filtered = (0..applications.len())
+            .filter(|&i| !applications[i].db_entry.blacklisted)
+            .collect::<Vec<usize>>();
+        let non_blacklisted_applications_len = filtered.len();
         Self {
-            filtered_applications,
+            search_matches: vec![None; filtered.len()],
+            filtered,
+            previous_filter: None,
             applications,
+            desktop_files: HashMap::new(),
+            loading: false,
+            loading_sort_duration: Duration::ZERO,
+            pending_changes: Vec::new(),
             list: ListState::default(),
             scrollbar: ScrollbarState::default(),
+            area: Rect::default(),
+            scrollbar_area: Rect::default(),
             non_blacklisted_applications_len,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launch_counts(entries: &[DbEntry]) -> Vec<(&str, usize)> {
+        entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.launch_count))
+            .collect()
+    }
+
+    #[test]
+    fn ranks_matches_by_score_with_the_configured_order() {
+        let config = Config::default();
+        let mut state = ApplicationListState::from_items(
+            ["Grafix", "Fish", "Firefox"].map(String::from).to_vec(),
+        );
+        state.update("fi", &config);
+        let names = state
+            .filtered
+            .iter()
+            .map(|&i| state.applications[i].name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["Fish", "Firefox", "Grafix"]);
+    }
+
+    #[test]
+    fn forgets_a_failed_first_launch_on_disk() {
+        let mut state =
+            ApplicationListState::from_items(vec![String::from("Foo"), String::from("Bar")]);
+        let foo = state.applications[0].clone();
+        let hidden = DbEntry {
+            launch_count: 3,
+            ..DbEntry::new("Hidden")
+        };
+
+        // saved before exec, as a successful one never returns
+        state.record_launch(&foo);
+        let on_disk = state.merged_db_entries(vec![hidden]);
+        assert_eq!(launch_counts(&on_disk), [("Foo", 1), ("Hidden", 3)]);
+
+        // exec failed, so the never launched entry is restored
+        state.set_db_entry(&foo, &foo.db_entry);
+        let on_disk = state.merged_db_entries(on_disk);
+        assert_eq!(launch_counts(&on_disk), [("Hidden", 3)]);
+    }
+
+    #[test]
+    fn searches_again_when_the_last_grapheme_grows() {
+        let config = Config::default();
+        let mut state = ApplicationListState::from_items(vec![String::from("Cafe\u{301} Noir")]);
+        state.update("cafe", &config);
+        assert_eq!(state.filtered_len(), 0);
+        state.update("cafe\u{301}", &config);
+        assert_eq!(state.filtered_len(), 1);
+        state.update("cafe\u{301} n", &config);
+        assert_eq!(state.filtered_len(), 1);
+    }
+}
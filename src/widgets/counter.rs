@@ -1,24 +1,33 @@
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Rect},
-    style::Style,
     widgets::{Paragraph, Widget},
 };
 
-use crate::config::Config;
+use crate::{config::Config, theme::Theme};
 
 pub struct Counter<'a> {
     config: &'a Config,
+    theme: &'a Theme,
     current: usize,
     max: usize,
+    loading: bool,
 }
 
 impl<'a> Counter<'a> {
-    pub fn new(config: &'a Config, current: usize, max: usize) -> Self {
+    pub fn new(
+        config: &'a Config,
+        theme: &'a Theme,
+        current: usize,
+        max: usize,
+        loading: bool,
+    ) -> Self {
         Self {
             config,
+            theme,
             current,
             max,
+            loading,
         }
     }
 }
@@ -28,8 +37,12 @@ impl Widget for Counter<'_> {
         if !self.config.counter.enable {
             return;
         }
-        let text = format!("{} / {}", self.current, self.max);
-        let mut style = Style::new();
+        let text = if self.loading {
+            String::from("loading…")
+        } else {
+            format!("{} / {}", self.current, self.max)
+        };
+        let mut style = self.theme.counter;
         if self.config.counter.bold {
             style = style.bold();
         }
@@ -4,15 +4,16 @@ use ratatui::{
     widgets::{Paragraph, StatefulWidget, Widget},
 };
 
-use crate::config::Config;
+use crate::{config::Config, theme::Theme};
 
 pub struct Info<'a> {
     config: &'a Config,
+    theme: &'a Theme,
 }
 
 impl<'a> Info<'a> {
-    pub fn new(config: &'a Config) -> Self {
-        Self { config }
+    pub fn new(config: &'a Config, theme: &'a Theme) -> Self {
+        Self { config, theme }
     }
 }
 
@@ -23,7 +24,7 @@ impl<'a> StatefulWidget for Info<'a> {
             return;
         }
         let message = state.message.clone().unwrap_or_default();
-        let paragraph = Paragraph::new(message);
+        let paragraph = Paragraph::new(message).style(self.theme.info);
         Widget::render(paragraph, area, buf);
     }
 }
@@ -1,19 +1,21 @@
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Position, Rect},
-    style::{Color, Style},
     widgets::{Paragraph, StatefulWidget, Widget},
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-use crate::config::Config;
+use crate::{config::Config, theme::Theme};
 
 pub struct Input<'a> {
     config: &'a Config,
+    theme: &'a Theme,
 }
 
 impl<'a> Input<'a> {
-    pub fn new(config: &'a Config) -> Self {
-        Self { config }
+    pub fn new(config: &'a Config, theme: &'a Theme) -> Self {
+        Self { config, theme }
     }
 }
 
@@ -23,7 +25,7 @@ impl StatefulWidget for Input<'_> {
         let icon_area_constraint_length = if self.config.input.icon.is_empty() {
             0
         } else {
-            self.config.input.icon.len() as u16 + 3
+            self.config.input.icon.width() as u16 + 3
         };
         let [icon_area, input_area] = Layout::horizontal([
             Constraint::Length(icon_area_constraint_length),
@@ -31,113 +33,335 @@ impl StatefulWidget for Input<'_> {
         ])
         .areas(area);
         let icon = Paragraph::new(self.config.input.icon.as_str());
-        state.width = (area.width - icon_area_constraint_length) as usize;
+        state.area = input_area;
+        state.width = usize::from(input_area.width);
+        state.scroll_to_cursor();
         let input_text = if state.filter.is_empty() {
-            Paragraph::new(self.config.input.placeholder.as_str())
-                .style(Style::new().fg(Color::DarkGray).italic())
+            Paragraph::new(self.config.input.placeholder.as_str()).style(self.theme.placeholder)
         } else {
-            let len = state.filter.len().min(state.width + state.overflow);
-            let filter_text_to_display = &state.filter[state.overflow..len];
-            Paragraph::new(filter_text_to_display)
+            Paragraph::new(state.visible_filter())
         };
         Widget::render(icon, icon_area, buf);
         Widget::render(input_text, input_area, buf);
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct InputState {
     pub filter: String,
-    pub cursor_index: usize,
+    /// byte index into `filter`, always on a grapheme boundary
+    cursor: usize,
+    /// display columns of `filter` scrolled out of view on the left
     overflow: usize,
     width: usize,
+    /// area of the last render, to map mouse clicks
+    area: Rect,
+    /// text removed by the last kill, inserted again by [`InputState::yank`]
+    killed: String,
 }
 
 impl InputState {
     pub fn relative_cursor_position(&self) -> Position {
-        Position::new(self.cursor_index as u16, 0)
+        let column = display_width(&self.filter[..self.cursor]);
+        Position::new(column.saturating_sub(self.overflow) as u16, 0)
     }
 
     pub fn move_cursor_left(&mut self) {
-        if self.cursor_index == 0 && self.overflow > 0 {
-            self.overflow = self.overflow.saturating_sub(1);
-        }
-        let cursor_moved_left = self.cursor_index.saturating_sub(1);
-        self.cursor_index = self.clamp_cursor(cursor_moved_left);
+        self.cursor = self.previous_boundary();
     }
 
     pub fn move_cursor_right(&mut self) {
-        let max_overflow = self.filter.len().saturating_sub(self.width);
-        if self.cursor_index == self.width && self.overflow < max_overflow {
-            self.overflow = self.overflow.saturating_add(1);
-        }
-        let cursor_moved_right = self.cursor_index.saturating_add(1);
-        self.cursor_index = self.clamp_cursor(cursor_moved_right);
+        self.cursor = self.next_boundary();
     }
 
     pub fn enter_char(&mut self, new_char: char) {
-        let index = self.byte_index();
-        self.filter.insert(index, new_char);
-        self.set_overflow();
-        if self.overflow == 0 {
-            self.move_cursor_right();
-        }
+        self.filter.insert(self.cursor, new_char);
+        self.cursor += new_char.len_utf8();
     }
 
     pub fn delete_char(&mut self) {
-        let is_cursor_leftmost = self.cursor_index + self.overflow == 0;
-        if is_cursor_leftmost {
-            return;
+        let start = self.previous_boundary();
+        self.filter.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    pub fn right_delete_char(&mut self) {
+        let end = self.next_boundary();
+        self.filter.drain(self.cursor..end);
+    }
+
+    /// Moves the cursor in front of the grapheme at `position`, if it lies within the input.
+    pub fn move_cursor_to(&mut self, position: Position) -> bool {
+        if !self.area.contains(position) {
+            return false;
         }
-        let current_index = self.cursor_index + self.overflow;
-        let from_left_to_current_index = current_index - 1;
-        let before_char_to_delete = self.filter.chars().take(from_left_to_current_index);
-        let after_char_to_delete = self.filter.chars().skip(current_index);
-        self.filter = before_char_to_delete.chain(after_char_to_delete).collect();
-        if self.overflow == 0 {
-            self.move_cursor_left();
+        let target = usize::from(position.x - self.area.x) + self.overflow;
+        let mut column = 0;
+        self.cursor = self.filter.len();
+        for (i, grapheme) in self.filter.grapheme_indices(true) {
+            column += grapheme.width();
+            if column > target {
+                self.cursor = i;
+                break;
+            }
         }
-        self.set_overflow();
+        true
     }
 
-    pub fn right_delete_char(&mut self) {
-        let is_cursor_rightmost = self.cursor_index + self.overflow == self.filter.len();
-        if is_cursor_rightmost {
+    pub fn move_cursor_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_cursor_to_end(&mut self) {
+        self.cursor = self.filter.len();
+    }
+
+    pub fn move_cursor_word_left(&mut self) {
+        self.cursor = self.previous_word_boundary();
+    }
+
+    pub fn move_cursor_word_right(&mut self) {
+        self.cursor = self.next_word_boundary();
+    }
+
+    pub fn delete_word(&mut self) {
+        let start = self.previous_word_boundary();
+        self.kill(start..self.cursor);
+    }
+
+    pub fn kill_to_start(&mut self) {
+        self.kill(0..self.cursor);
+    }
+
+    pub fn kill_to_end(&mut self) {
+        self.kill(self.cursor..self.filter.len());
+    }
+
+    pub fn yank(&mut self) {
+        self.filter.insert_str(self.cursor, &self.killed);
+        self.cursor += self.killed.len();
+    }
+
+    /// Removes `range` of `filter`, keeping it for [`InputState::yank`].
+    fn kill(&mut self, range: std::ops::Range<usize>) {
+        if range.is_empty() {
             return;
         }
-        let index = self.cursor_index + self.overflow;
-        if self.overflow > 0 {
-            self.move_cursor_right();
+        self.cursor = range.start;
+        self.killed = self.filter.drain(range).collect();
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.filter[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.filter[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    /// Start of the word before the cursor, skipping any separators in between.
+    fn previous_word_boundary(&self) -> usize {
+        let mut boundary = self.cursor;
+        let mut in_word = false;
+        for (i, grapheme) in self.filter[..self.cursor].grapheme_indices(true).rev() {
+            if is_word(grapheme) {
+                in_word = true;
+            } else if in_word {
+                break;
+            }
+            boundary = i;
         }
-        self.filter.remove(index);
-        self.set_overflow();
+        boundary
     }
 
-    fn set_overflow(&mut self) {
-        self.overflow = self.filter.len().saturating_sub(self.width);
+    /// End of the word after the cursor, skipping any separators in between.
+    fn next_word_boundary(&self) -> usize {
+        let mut boundary = self.cursor;
+        let mut in_word = false;
+        for grapheme in self.filter[self.cursor..].graphemes(true) {
+            if is_word(grapheme) {
+                in_word = true;
+            } else if in_word {
+                break;
+            }
+            boundary += grapheme.len();
+        }
+        boundary
     }
 
-    fn byte_index(&self) -> usize {
-        self.filter
-            .char_indices()
-            .map(|(i, _)| i)
-            .nth(self.cursor_index + self.overflow)
-            .unwrap_or(self.filter.len())
+    /// Scrolls just far enough to keep the cursor in view, without leaving empty space on the right.
+    fn scroll_to_cursor(&mut self) {
+        let column = display_width(&self.filter[..self.cursor]);
+        if column < self.overflow {
+            self.overflow = column;
+        } else if self.width > 0 && column >= self.overflow + self.width {
+            self.overflow = column + 1 - self.width;
+        }
+        // one extra column for the cursor behind the last grapheme
+        let max_overflow = (display_width(&self.filter) + 1).saturating_sub(self.width);
+        self.overflow = self.overflow.min(max_overflow);
     }
 
-    fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
-        let max = self.filter.chars().count().min(self.width);
-        new_cursor_pos.clamp(0, max)
+    /// The part of `filter` in view, padding wide graphemes cut off on the left.
+    fn visible_filter(&self) -> String {
+        let mut visible = String::new();
+        let mut column = 0;
+        for grapheme in self.filter.graphemes(true) {
+            let width = grapheme.width();
+            let end = column + width;
+            if column < self.overflow && end > self.overflow {
+                visible.extend(std::iter::repeat_n(' ', end - self.overflow));
+            } else if column >= self.overflow && end <= self.overflow + self.width {
+                visible.push_str(grapheme);
+            }
+            column = end;
+        }
+        visible
     }
 }
 
-impl Default for InputState {
-    fn default() -> Self {
-        Self {
-            filter: String::new(),
-            cursor_index: 0,
-            width: 32,
-            overflow: 0,
-        }
+/// Width of `text` in terminal columns, measured per grapheme like ratatui does.
+fn display_width(text: &str) -> usize {
+    text.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str, width: usize) -> InputState {
+        let mut input = InputState {
+            width,
+            ..InputState::default()
+        };
+        text.chars().for_each(|c| input.enter_char(c));
+        input.scroll_to_cursor();
+        input
+    }
+
+    #[test]
+    fn measures_cjk_in_display_columns() {
+        let mut input = typed("日本", 32);
+        assert_eq!(input.relative_cursor_position(), Position::new(4, 0));
+        input.move_cursor_left();
+        assert_eq!(input.relative_cursor_position(), Position::new(2, 0));
+        input.delete_char();
+        assert_eq!(input.filter, "本");
+        assert_eq!(input.relative_cursor_position(), Position::new(0, 0));
+    }
+
+    #[test]
+    fn edits_emoji_sequences_as_a_whole() {
+        let mut input = typed("a👩‍💻", 32);
+        assert_eq!(input.relative_cursor_position(), Position::new(3, 0));
+        input.delete_char();
+        assert_eq!(input.filter, "a");
+        let mut input = typed("🇩🇪x", 32);
+        input.move_cursor_left();
+        input.move_cursor_left();
+        assert_eq!(input.relative_cursor_position(), Position::new(0, 0));
+        input.right_delete_char();
+        assert_eq!(input.filter, "x");
+    }
+
+    #[test]
+    fn moves_over_combining_characters() {
+        let mut input = typed("e\u{301}", 32);
+        assert_eq!(input.relative_cursor_position(), Position::new(1, 0));
+        input.move_cursor_left();
+        assert_eq!(input.relative_cursor_position(), Position::new(0, 0));
+        input.right_delete_char();
+        assert_eq!(input.filter, "");
+    }
+
+    #[test]
+    fn scrolls_wide_graphemes_out_of_view() {
+        let input = typed("日本語", 4);
+        assert_eq!(input.visible_filter(), " 語");
+        assert_eq!(input.relative_cursor_position(), Position::new(3, 0));
+    }
+
+    #[test]
+    fn moves_cursor_to_clicked_column() {
+        let mut input = typed("日本語abc", 4);
+        input.area = Rect::new(10, 0, 4, 1);
+        assert_eq!(input.visible_filter(), "abc");
+        assert!(input.move_cursor_to(Position::new(11, 0)));
+        assert_eq!(input.relative_cursor_position(), Position::new(1, 0));
+        assert!(input.move_cursor_to(Position::new(13, 0)));
+        assert_eq!(input.relative_cursor_position(), Position::new(3, 0));
+        input.move_cursor_to_start();
+        input.scroll_to_cursor();
+        assert!(input.move_cursor_to(Position::new(11, 0)));
+        assert_eq!(input.relative_cursor_position(), Position::new(0, 0));
+        assert!(!input.move_cursor_to(Position::new(9, 0)));
+    }
+
+    #[test]
+    fn moves_and_deletes_by_word() {
+        let mut input = typed("open  web-browser", 32);
+        input.move_cursor_word_left();
+        assert_eq!(input.relative_cursor_position(), Position::new(10, 0));
+        input.move_cursor_word_left();
+        assert_eq!(input.relative_cursor_position(), Position::new(6, 0));
+        input.move_cursor_word_right();
+        assert_eq!(input.relative_cursor_position(), Position::new(9, 0));
+        input.move_cursor_to_end();
+        input.delete_word();
+        assert_eq!(input.filter, "open  web-");
+        input.delete_word();
+        assert_eq!(input.filter, "open  ");
+        input.delete_word();
+        assert_eq!(input.filter, "");
+    }
+
+    #[test]
+    fn yanks_the_last_kill() {
+        let mut input = typed("firefox nightly", 32);
+        input.move_cursor_word_left();
+        input.kill_to_end();
+        assert_eq!(input.filter, "firefox ");
+        input.move_cursor_to_start();
+        input.yank();
+        assert_eq!(input.filter, "nightlyfirefox ");
+        assert_eq!(input.relative_cursor_position(), Position::new(7, 0));
+        input.kill_to_start();
+        assert_eq!(input.filter, "firefox ");
+        input.move_cursor_to_end();
+        input.yank();
+        assert_eq!(input.filter, "firefox nightly");
+    }
+
+    #[test]
+    fn scrolls_with_jumps_and_kills() {
+        let mut input = typed("abcdefghij", 4);
+        assert_eq!(input.visible_filter(), "hij");
+        input.move_cursor_to_start();
+        input.scroll_to_cursor();
+        assert_eq!(input.visible_filter(), "abcd");
+        assert_eq!(input.relative_cursor_position(), Position::new(0, 0));
+        input.move_cursor_to_end();
+        input.scroll_to_cursor();
+        assert_eq!(input.relative_cursor_position(), Position::new(3, 0));
+        input.move_cursor_word_left();
+        input.kill_to_end();
+        input.yank();
+        input.scroll_to_cursor();
+        assert_eq!(input.visible_filter(), "hij");
+        input.kill_to_start();
+        input.scroll_to_cursor();
+        assert_eq!(input.filter, "");
+        assert_eq!(input.visible_filter(), "");
+        assert_eq!(input.relative_cursor_position(), Position::new(0, 0));
     }
 }
@@ -1,8 +1,25 @@
-use std::{sync::mpsc, thread};
+use std::{
+    collections::HashMap,
+    os::fd::AsFd,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
 
-use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::{
+    poll::{PollFd, PollFlags, poll},
+    sys::{
+        inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor},
+        signal::{SigSet, Signal},
+        wait::{WaitPidFlag, WaitStatus, waitpid},
+    },
+};
 
-use crate::{config::Config, message::Message};
+use crate::{
+    application::Application, cache::Cache, config::Config, db::Db, locale::Locale,
+    message::Message, xdg,
+};
 use crossterm::event::{self, Event, KeyEventKind};
 
 pub struct FileWatcher;
@@ -60,6 +77,8 @@ impl EventWatcher {
                         }
                     }
 
+                    Event::Mouse(mouse_event) => sender.send(Message::Mouse(mouse_event)).unwrap(),
+
                     Event::Resize(_, _) => sender.send(Message::Redraw).unwrap(),
 
                     _ => continue,
@@ -68,3 +87,224 @@ impl EventWatcher {
         });
     }
 }
+
+pub struct ChildReaper;
+
+impl ChildReaper {
+    /// Blocks SIGCHLD and reaps exited children on a dedicated thread.
+    ///
+    /// Has to be spawned before any other thread, so they all inherit the blocked signal.
+    pub fn spawn(sender: mpsc::Sender<Message>) {
+        let mut mask = SigSet::empty();
+        mask.add(Signal::SIGCHLD);
+        mask.thread_block().unwrap();
+        thread::spawn(move || {
+            loop {
+                if mask.wait().is_err() {
+                    continue;
+                }
+                while let Ok(status) = waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+                    if status == WaitStatus::StillAlive {
+                        break;
+                    }
+                    sender.send(Message::ChildExited(status)).unwrap();
+                }
+            }
+        });
+    }
+}
+
+pub struct ApplicationWatcher;
+
+impl ApplicationWatcher {
+    /// how long to wait for further events before reporting a batch of changes
+    const DEBOUNCE_MS: u16 = 100;
+
+    /// Watches every application directory and reports changed paths in batches.
+    ///
+    /// Application directories that do not exist yet are waited for by watching their nearest existing parent.
+    pub fn spawn(sender: mpsc::Sender<Message>) {
+        thread::spawn(move || {
+            let inotify = Inotify::init(InitFlags::IN_CLOEXEC).unwrap();
+            let mut watches = Watches {
+                dirs: HashMap::new(),
+                parents: HashMap::new(),
+                missing: xdg::application_dirs(),
+            };
+            watches.watch_missing(&inotify, &mut Vec::new());
+            loop {
+                let mut changed = Vec::new();
+                let Ok(events) = inotify.read_events() else {
+                    continue;
+                };
+                watches.collect(&inotify, events, &mut changed);
+                let mut fds = [PollFd::new(inotify.as_fd(), PollFlags::POLLIN)];
+                while poll(&mut fds, Self::DEBOUNCE_MS).is_ok_and(|ready| ready > 0) {
+                    let Ok(events) = inotify.read_events() else {
+                        break;
+                    };
+                    watches.collect(&inotify, events, &mut changed);
+                }
+                if !changed.is_empty() {
+                    sender.send(Message::ApplicationsChanged(changed)).unwrap();
+                }
+            }
+        });
+    }
+}
+
+/// inotify watches of the [`ApplicationWatcher`].
+struct Watches {
+    /// watched directories containing desktop files
+    dirs: HashMap<WatchDescriptor, PathBuf>,
+    /// nearest existing parents of the application directories in `missing`
+    parents: HashMap<WatchDescriptor, PathBuf>,
+    /// application directories that do not exist yet
+    missing: Vec<PathBuf>,
+}
+
+impl Watches {
+    const DIR_FLAGS: AddWatchFlags = AddWatchFlags::IN_CREATE
+        .union(AddWatchFlags::IN_DELETE)
+        .union(AddWatchFlags::IN_CLOSE_WRITE)
+        .union(AddWatchFlags::IN_MOVED_FROM)
+        .union(AddWatchFlags::IN_MOVED_TO)
+        .union(AddWatchFlags::IN_ATTRIB);
+    /// added to whatever a parent is already watched for, as it may be a watched directory itself
+    const PARENT_FLAGS: AddWatchFlags = AddWatchFlags::IN_CREATE
+        .union(AddWatchFlags::IN_MOVED_TO)
+        // IN_MASK_ADD, which nix does not expose
+        .union(AddWatchFlags::from_bits_retain(0x2000_0000));
+
+    fn watch_recursive(&mut self, inotify: &Inotify, dir: &Path) {
+        let Ok(wd) = inotify.add_watch(dir, Self::DIR_FLAGS) else {
+            return;
+        };
+        // the kernel hands out one descriptor per inode, so a known one means a
+        // directory reached again through a symlink, possibly in a loop
+        if self.dirs.contains_key(&wd) {
+            return;
+        }
+        self.dirs.insert(wd, dir.to_path_buf());
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                self.watch_recursive(inotify, &entry.path());
+            }
+        }
+    }
+
+    /// Watches application directories that appeared, and the nearest existing parent of those still missing.
+    fn watch_missing(&mut self, inotify: &Inotify, changed: &mut Vec<PathBuf>) {
+        for dir in std::mem::take(&mut self.missing) {
+            if dir.is_dir() {
+                self.watch_recursive(inotify, &dir);
+                // it may already contain files created before it was watched
+                changed.push(dir);
+                continue;
+            }
+            if let Some(parent) = dir.ancestors().skip(1).find(|parent| parent.is_dir())
+                && let Ok(wd) = inotify.add_watch(parent, Self::PARENT_FLAGS)
+            {
+                self.parents.insert(wd, parent.to_path_buf());
+            }
+            self.missing.push(dir);
+        }
+    }
+
+    fn collect(
+        &mut self,
+        inotify: &Inotify,
+        events: Vec<nix::sys::inotify::InotifyEvent>,
+        changed: &mut Vec<PathBuf>,
+    ) {
+        let mut created_parent = false;
+        for event in events {
+            if event.mask.contains(AddWatchFlags::IN_IGNORED) {
+                self.dirs.remove(&event.wd);
+                self.parents.remove(&event.wd);
+                continue;
+            }
+            let created_dir = event.mask.contains(AddWatchFlags::IN_ISDIR)
+                && event
+                    .mask
+                    .intersects(AddWatchFlags::IN_CREATE | AddWatchFlags::IN_MOVED_TO);
+            created_parent |= created_dir && self.parents.contains_key(&event.wd);
+            let (Some(dir), Some(name)) = (self.dirs.get(&event.wd), event.name) else {
+                continue;
+            };
+            let path = dir.join(name);
+            if created_dir {
+                self.watch_recursive(inotify, &path);
+            }
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+        if created_parent {
+            self.watch_missing(inotify, changed);
+        }
+    }
+}
+
+pub struct ApplicationLoader;
+
+impl ApplicationLoader {
+    const BATCH_SIZE: usize = 256;
+    /// minimum time between two batches, so the UI is not re-sorting on every few files
+    const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Scans and parses desktop entries, streaming them to the UI in batches.
+    ///
+    /// Unchanged entries come from the [`Cache`], which starts over if `rebuild_cache` is set.
+    pub fn spawn(sender: mpsc::Sender<Message>, rebuild_cache: bool) {
+        thread::spawn(move || {
+            let mut timings = LoadTimings::default();
+            let config = Config::load().application_list;
+            let locales = Locale::from_env().candidates();
+            let start = Instant::now();
+            let db = Db::load();
+            timings.db_merge += start.elapsed();
+            let start = Instant::now();
+            let mut cache = Cache::load(rebuild_cache, &locales);
+            timings.parse += start.elapsed();
+            let start = Instant::now();
+            let desktop_files = cache.desktop_files();
+            timings.scan += start.elapsed();
+            let mut applications = Vec::new();
+            let mut last_sent = Instant::now();
+            for batch in desktop_files.chunks(Self::BATCH_SIZE) {
+                let start = Instant::now();
+                let entries = cache.entries(batch);
+                timings.parse += start.elapsed();
+                let start = Instant::now();
+                applications.extend(Application::from_entries(entries, &config, &db));
+                timings.db_merge += start.elapsed();
+                if last_sent.elapsed() >= Self::BATCH_INTERVAL {
+                    let batch = std::mem::take(&mut applications);
+                    sender.send(Message::ApplicationsLoaded(batch)).unwrap();
+                    last_sent = Instant::now();
+                }
+            }
+            sender
+                .send(Message::ApplicationsLoaded(applications))
+                .unwrap();
+            sender
+                .send(Message::ApplicationsLoadingFinished(desktop_files, timings))
+                .unwrap();
+            cache.save();
+        });
+    }
+}
+
+/// Time spent in each phase of loading the applications.
+#[derive(Debug, Default)]
+pub struct LoadTimings {
+    pub scan: Duration,
+    pub parse: Duration,
+    pub db_merge: Duration,
+    /// filled in by the UI, which sorts every batch it receives
+    pub sort: Duration,
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashSet,
+    env::{self, home_dir},
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share/:/usr/share/";
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn data_home() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| home_dir().unwrap().join(".local/share"))
+}
+
+/// `$XDG_CACHE_HOME`, falling back to `~/.cache`.
+pub fn cache_home() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| home_dir().unwrap().join(".cache"))
+}
+
+/// `$XDG_DATA_DIRS`, falling back to `/usr/local/share:/usr/share`.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| DEFAULT_DATA_DIRS.to_string());
+    env::split_paths(&dirs)
+        .filter(|path| path.is_absolute())
+        .collect()
+}
+
+/// All `applications` directories, ordered from highest to lowest precedence.
+pub fn application_dirs() -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    std::iter::once(data_home())
+        .chain(data_dirs())
+        .map(|dir| dir.join("applications"))
+        .filter(|dir| seen.insert(dir.clone()))
+        .collect()
+}
+
+/// A `.desktop` file together with its desktop file ID.
+#[derive(Clone, Debug)]
+pub struct DesktopFile {
+    pub id: String,
+    pub path: PathBuf,
+}
+
+/// Collects every desktop file in the application directories.
+///
+/// Entries in subdirectories get IDs like `kde-org.kate.desktop`, and when two
+/// files share an ID, the one from the directory with higher precedence wins.
+pub fn desktop_files() -> Vec<DesktopFile> {
+    scan().0
+}
+
+/// Like [`desktop_files`], but also returns every directory that was searched,
+/// including application directories that do not exist.
+pub fn scan() -> (Vec<DesktopFile>, Vec<PathBuf>) {
+    let mut seen = HashSet::new();
+    let mut desktop_files = Vec::new();
+    let mut dirs = Vec::new();
+    for dir in application_dirs() {
+        let mut found = Vec::new();
+        collect_desktop_files(&dir, &dir, &mut found, &mut dirs, &mut HashSet::new());
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+        desktop_files.extend(
+            found
+                .into_iter()
+                .filter(|desktop_file| seen.insert(desktop_file.id.clone())),
+        );
+    }
+    (desktop_files, dirs)
+}
+
+fn collect_desktop_files(
+    root: &Path,
+    dir: &Path,
+    found: &mut Vec<DesktopFile>,
+    dirs: &mut Vec<PathBuf>,
+    visited: &mut HashSet<(u64, u64)>,
+) {
+    // symlinks are followed, so a directory reached twice is part of a loop or already collected
+    if dir_key(dir).is_some_and(|key| !visited.insert(key)) {
+        return;
+    }
+    dirs.push(dir.to_path_buf());
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_desktop_files(root, &path, found, dirs, visited);
+        } else if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("desktop") {
+            let Some(id) = desktop_file_id(root, &path) else {
+                continue;
+            };
+            found.push(DesktopFile { id, path });
+        }
+    }
+}
+
+/// Device and inode of `path`, following symlinks, to recognize a directory reached twice.
+pub fn dir_key(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+fn desktop_file_id(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let components = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<&str>>>()?;
+    Some(components.join("-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survives_symlink_loops() {
+        let root = env::temp_dir().join(format!("spellbook-xdg-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("kde")).unwrap();
+        fs::write(root.join("kde/kate.desktop"), "").unwrap();
+        std::os::unix::fs::symlink("..", root.join("kde/loop")).unwrap();
+        std::os::unix::fs::symlink(".", root.join("self")).unwrap();
+        let (mut found, mut dirs) = (Vec::new(), Vec::new());
+        collect_desktop_files(&root, &root, &mut found, &mut dirs, &mut HashSet::new());
+        fs::remove_dir_all(&root).unwrap();
+        let ids = found
+            .into_iter()
+            .map(|file| file.id)
+            .collect::<Vec<String>>();
+        assert_eq!(ids, ["kde-kate.desktop"]);
+        assert_eq!(dirs.len(), 2);
+    }
+}